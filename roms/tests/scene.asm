@@ -0,0 +1,123 @@
+; scene.nes: a screenshot test image for NROM. A striped background over four attribute
+; palettes, eight sprites with every palette and flip, and two of them moved by the NMI
+; each frame. ca65 syntax, 16KB PRG at $C000 and 8KB CHR.
+
+.segment "HEADER"
+    .byte "NES", $1A, $01, $01, $00, $00, $00, $00, $00, $00, $00, $00, $00, $00
+
+.segment "CODE"
+reset:
+    sei
+    cld
+    ldx #$FF
+    txs
+    lda #$00
+    sta $2000
+    sta $2001
+wait_vblank1:
+    bit $2002
+    bpl wait_vblank1
+wait_vblank2:
+    bit $2002
+    bpl wait_vblank2
+
+    lda #$3F
+    sta $2006
+    lda #$00
+    sta $2006
+    ldx #$00
+load_palette:
+    lda palette,x
+    sta $2007
+    inx
+    cpx #$20
+    bne load_palette
+
+    ; nametable 0 and its attribute table, four 256 byte pages of tiles 1-4
+    lda #$20
+    sta $2006
+    lda #$00
+    sta $2006
+    ldy #$04
+fill_page:
+    ldx #$00
+fill_byte:
+    txa
+    and #$03
+    clc
+    adc #$01
+    sta $2007
+    inx
+    bne fill_byte
+    dey
+    bne fill_page
+
+    lda #$FF
+    ldx #$00
+hide_sprites:
+    sta $0200,x
+    inx
+    bne hide_sprites
+    ldx #$00
+load_sprites:
+    lda sprites,x
+    sta $0200,x
+    inx
+    cpx #$20
+    bne load_sprites
+    lda #$02
+    sta $4014
+
+    lda #$00
+    sta $2005
+    sta $2005
+    lda #$80
+    sta $2000
+    lda #$1E
+    sta $2001
+forever:
+    jmp forever
+
+nmi:
+    inc $0203
+    inc $0204
+    lda #$00
+    sta $2003
+    lda #$02
+    sta $4014
+irq:
+    rti
+
+palette:
+    .byte $0F, $01, $11, $21, $0F, $06, $16, $26, $0F, $09, $19, $29, $0F, $04, $14, $24
+    .byte $0F, $30, $27, $16, $0F, $2A, $1A, $0A, $0F, $12, $22, $32, $0F, $15, $25, $35
+
+; Y, tile, attributes, X
+sprites:
+    .byte $30, $05, $00, $20
+    .byte $40, $05, $01, $40
+    .byte $50, $05, $02, $60
+    .byte $60, $05, $03, $80
+    .byte $70, $06, $40, $A0
+    .byte $80, $06, $80, $C0
+    .byte $90, $06, $21, $D0
+    .byte $A0, $06, $C2, $E0
+
+.segment "VECTORS"
+    .word nmi, reset, irq
+
+.segment "CHARS"
+    ; 0: blank
+    .byte $00, $00, $00, $00, $00, $00, $00, $00, $00, $00, $00, $00, $00, $00, $00, $00
+    ; 1: solid colour 1
+    .byte $FF, $FF, $FF, $FF, $FF, $FF, $FF, $FF, $00, $00, $00, $00, $00, $00, $00, $00
+    ; 2: colour 2 checkerboard
+    .byte $00, $00, $00, $00, $00, $00, $00, $00, $AA, $55, $AA, $55, $AA, $55, $AA, $55
+    ; 3: colour 3 lines
+    .byte $FF, $00, $FF, $00, $FF, $00, $FF, $00, $FF, $00, $FF, $00, $FF, $00, $FF, $00
+    ; 4: box with a square inside
+    .byte $FF, $81, $81, $81, $81, $81, $81, $FF, $00, $00, $3C, $24, $24, $3C, $00, $00
+    ; 5: ball
+    .byte $3C, $7E, $FF, $FF, $FF, $FF, $7E, $3C, $00, $18, $3C, $7E, $7E, $3C, $18, $00
+    ; 6: arrow, so the flips show
+    .byte $10, $30, $7F, $FF, $7F, $30, $10, $00, $00, $10, $30, $7F, $30, $10, $00, $00
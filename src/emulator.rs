@@ -1,7 +1,15 @@
-mod apu;
+pub mod apu;
 pub mod bus;
+pub mod cdl;
 pub mod cpu;
 pub mod joypad;
 mod memory;
 pub mod ppu;
+pub mod recorder;
 pub mod rom;
+#[cfg(test)]
+mod screenshot_test;
+#[cfg(test)]
+mod test_helpers;
+#[cfg(test)]
+mod test_rom;
@@ -1,16 +1,24 @@
+mod blip;
 mod channels;
+mod filter;
+pub mod mixer;
 mod regs;
 
+use blip::BlipBuffer;
 use channels::dmc::Dmc;
 use channels::noise::Noise;
 use channels::pulse::Pulse;
 use channels::triangle::Triangle;
+use filter::FilterChain;
+use mixer::Mixer;
 use regs::Reg;
 use regs::envelope::ENVELOPE_COUNTER_HALT_MASK;
 use regs::frame_counter::*;
 use regs::load_counter::LOAD_COUNTER_MASK;
 use regs::status::*;
 
+pub const CPU_CLOCK_RATE: f64 = 1_789_773.0;
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
 const NUM_OF_PULSE_CHANNELS: usize = 2;
 
 pub struct Apu {
@@ -20,6 +28,10 @@ pub struct Apu {
     dmc: Dmc,
     status: Status,
     frame_counter: FrameCounter,
+    mixer: Mixer,
+    blip: BlipBuffer,
+    filters: FilterChain,
+    level: f32,
     global_cycle: usize,
 }
 
@@ -36,6 +48,10 @@ impl Apu {
             dmc: Dmc::new(),
             status: Status::new(),
             frame_counter: FrameCounter::new(),
+            mixer: Mixer::default(),
+            blip: BlipBuffer::new(CPU_CLOCK_RATE, DEFAULT_SAMPLE_RATE),
+            filters: FilterChain::nes(DEFAULT_SAMPLE_RATE),
+            level: 0.0,
             global_cycle: 0,
         }
     }
@@ -59,9 +75,33 @@ impl Apu {
             self.dmc.step_timer();
         }
 
+        // the output is sampled every cycle, only the changes go into the buffer
+        let level = self.get_audio_sample();
+        if level != self.level {
+            self.blip.add_delta(level - self.level);
+            self.level = level;
+        }
+        self.blip.advance(1);
+
         self.global_cycle += 1;
     }
 
+    // band-limited, resampled and filtered like the console's output stage
+    pub fn next_sample(&mut self) -> Option<f32> {
+        self.blip
+            .read_sample()
+            .map(|sample| self.filters.process(sample))
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.blip.get_sample_rate()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.blip.set_sample_rate(sample_rate);
+        self.filters = FilterChain::nes(sample_rate);
+    }
+
     fn step_quarter_frame(&mut self) {
         for pulse in self.pulses.iter_mut() {
             pulse.envelope_tick();
@@ -90,17 +130,21 @@ impl Apu {
     }
 
     pub fn get_audio_sample(&self) -> f32 {
-        let p1 = self.pulses[0].output();
-        let p2 = self.pulses[1].output();
-        let tri = self.triangle.output();
-        let noise = self.noise.output();
-        let dmc = self.dmc.output();
+        self.mixer.mix([
+            self.pulses[0].output(),
+            self.pulses[1].output(),
+            self.triangle.output(),
+            self.noise.output(),
+            self.dmc.output(),
+        ])
+    }
 
-        // Siplefied Mixer formula (Approximation)
-        let pulse_out = 0.00752 * (p1 + p2) as f32;
-        let tnd_out = (0.00851 * tri as f32) + (0.00494 * noise as f32) + (0.00335 * dmc as f32);
+    pub fn get_mixer(&self) -> Mixer {
+        self.mixer
+    }
 
-        pulse_out + tnd_out
+    pub fn set_mixer(&mut self, mixer: Mixer) {
+        self.mixer = mixer;
     }
 
     pub fn write_register(&mut self, addr: u16, data: u8) {
@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+// each step is spread over this many output samples, centred on the middle one
+pub const KERNEL_WIDTH: usize = 16;
+const HALF_WIDTH: f64 = (KERNEL_WIDTH / 2) as f64;
+const PHASES: usize = 64;
+// fraction of the output rate the kernel passes, just under Nyquist
+const CUTOFF: f64 = 0.9;
+
+lazy_static! {
+    static ref KERNEL: [[f32; KERNEL_WIDTH]; PHASES] = std::array::from_fn(step_kernel);
+}
+
+// records level changes at CPU clock resolution and reads them back as band-limited
+// samples at any output rate, every step turns into a windowed sinc impulse that is integrated
+pub struct BlipBuffer {
+    clock_rate: f64,
+    sample_rate: u32,
+    samples_per_clock: f64,
+    // output position of the current clock, relative to the front of the buffer
+    time: f64,
+    impulses: VecDeque<f32>,
+    integrator: f32,
+}
+
+impl BlipBuffer {
+    pub fn new(clock_rate: f64, sample_rate: u32) -> Self {
+        BlipBuffer {
+            clock_rate,
+            sample_rate,
+            samples_per_clock: sample_rate as f64 / clock_rate,
+            time: 0.0,
+            impulses: VecDeque::new(),
+            integrator: 0.0,
+        }
+    }
+
+    pub fn get_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // the output keeps its level, only the spacing of the samples changes
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        *self = BlipBuffer {
+            integrator: self.integrator,
+            ..BlipBuffer::new(self.clock_rate, sample_rate)
+        };
+    }
+
+    // a step of delta at the current clock
+    pub fn add_delta(&mut self, delta: f32) {
+        let index = self.time as usize;
+        let phase = ((self.time.fract() * PHASES as f64) as usize).min(PHASES - 1);
+
+        if self.impulses.len() < index + KERNEL_WIDTH {
+            self.impulses.resize(index + KERNEL_WIDTH, 0.0);
+        }
+        for (i, weight) in KERNEL[phase].iter().enumerate() {
+            self.impulses[index + i] += delta * weight;
+        }
+    }
+
+    pub fn advance(&mut self, clocks: usize) {
+        self.time += clocks as f64 * self.samples_per_clock;
+    }
+
+    // samples behind the current clock can't be touched by later steps
+    pub fn read_sample(&mut self) -> Option<f32> {
+        if self.time < 1.0 {
+            return None;
+        }
+        self.time -= 1.0;
+
+        self.integrator += self.impulses.pop_front().unwrap_or(0.0);
+        Some(self.integrator)
+    }
+}
+
+// windowed sinc for a step landing PHASE / PHASES of a sample late, summing to one
+fn step_kernel(phase: usize) -> [f32; KERNEL_WIDTH] {
+    let offset = HALF_WIDTH + phase as f64 / PHASES as f64;
+    let taps: [f64; KERNEL_WIDTH] = std::array::from_fn(|i| {
+        let x = i as f64 - offset;
+        sinc(CUTOFF * x) * blackman(x / HALF_WIDTH)
+    });
+    let sum: f64 = taps.iter().sum();
+
+    taps.map(|tap| (tap / sum) as f32)
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// x runs from -1 to 1 across the window
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+}
+
+#[cfg(test)]
+mod test;
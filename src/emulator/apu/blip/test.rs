@@ -0,0 +1,64 @@
+use super::*;
+
+const CLOCK_RATE: f64 = 1_789_773.0;
+const SAMPLE_RATE: u32 = 44100;
+
+fn run(blip: &mut BlipBuffer, clocks: usize) -> Vec<f32> {
+    let mut samples = Vec::new();
+    for _ in 0..clocks {
+        blip.advance(1);
+        while let Some(sample) = blip.read_sample() {
+            samples.push(sample);
+        }
+    }
+    samples
+}
+
+#[test]
+fn kernels_sum_to_one() {
+    for kernel in KERNEL.iter() {
+        let sum: f32 = kernel.iter().sum();
+        assert!((sum - 1.0).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn one_second_of_clocks_gives_one_second_of_samples() {
+    // the last sample of the second may only be finished on the next clock
+    let mut blip = BlipBuffer::new(CLOCK_RATE, SAMPLE_RATE);
+    let samples = run(&mut blip, CLOCK_RATE as usize + 1).len();
+    assert_eq!(samples, SAMPLE_RATE as usize);
+
+    blip.set_sample_rate(48000);
+    let samples = run(&mut blip, CLOCK_RATE as usize + 1).len();
+    assert_eq!(samples, 48000);
+}
+
+#[test]
+fn steps_settle_on_the_new_level() {
+    let mut blip = BlipBuffer::new(CLOCK_RATE, SAMPLE_RATE);
+    run(&mut blip, 1000);
+    blip.add_delta(0.5);
+
+    let samples = run(&mut blip, 2000);
+    assert!(samples[0].abs() < 1e-3);
+    // the step is centred half a kernel later
+    assert!((samples[KERNEL_WIDTH / 2] - 0.25).abs() < 0.1);
+    assert!((samples.last().unwrap() - 0.5).abs() < 1e-5);
+}
+
+#[test]
+fn tones_above_nyquist_are_removed() {
+    // a square wave at half the clock rate, far above what 44.1kHz can hold
+    let mut blip = BlipBuffer::new(CLOCK_RATE, SAMPLE_RATE);
+    let mut samples = Vec::new();
+    for cycle in 0..100_000 {
+        blip.add_delta(if cycle % 2 == 0 { 1.0 } else { -1.0 });
+        samples.extend(run(&mut blip, 1));
+    }
+
+    // all that is left is the average level
+    for sample in &samples[KERNEL_WIDTH..] {
+        assert!((sample - 0.5).abs() < 0.05, "{sample}");
+    }
+}
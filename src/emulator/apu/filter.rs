@@ -0,0 +1,81 @@
+use std::f32::consts::PI;
+
+// the console's output stage, see nesdev "APU Mixer"
+const HIGH_PASS_1_HZ: f32 = 90.0;
+const HIGH_PASS_2_HZ: f32 = 440.0;
+const LOW_PASS_HZ: f32 = 14_000.0;
+
+// first order RC filters, run at the output rate
+#[derive(Clone, Copy)]
+pub struct HighPass {
+    alpha: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+impl HighPass {
+    pub fn new(cutoff: f32, sample_rate: u32) -> Self {
+        let (rc, dt) = rc_and_dt(cutoff, sample_rate);
+        HighPass {
+            alpha: rc / (rc + dt),
+            prev_input: 0.0,
+            prev_output: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.prev_output = self.alpha * (self.prev_output + input - self.prev_input);
+        self.prev_input = input;
+        self.prev_output
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LowPass {
+    alpha: f32,
+    prev_output: f32,
+}
+
+impl LowPass {
+    pub fn new(cutoff: f32, sample_rate: u32) -> Self {
+        let (rc, dt) = rc_and_dt(cutoff, sample_rate);
+        LowPass {
+            alpha: dt / (rc + dt),
+            prev_output: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.prev_output += self.alpha * (input - self.prev_output);
+        self.prev_output
+    }
+}
+
+pub struct FilterChain {
+    high_pass_1: HighPass,
+    high_pass_2: HighPass,
+    low_pass: LowPass,
+}
+
+impl FilterChain {
+    pub fn nes(sample_rate: u32) -> Self {
+        FilterChain {
+            high_pass_1: HighPass::new(HIGH_PASS_1_HZ, sample_rate),
+            high_pass_2: HighPass::new(HIGH_PASS_2_HZ, sample_rate),
+            low_pass: LowPass::new(LOW_PASS_HZ, sample_rate),
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.high_pass_1.process(input);
+        let output = self.high_pass_2.process(output);
+        self.low_pass.process(output)
+    }
+}
+
+fn rc_and_dt(cutoff: f32, sample_rate: u32) -> (f32, f32) {
+    (1.0 / (2.0 * PI * cutoff), 1.0 / sample_rate as f32)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,55 @@
+use super::*;
+
+const SAMPLE_RATE: u32 = 44100;
+
+fn tone_amplitude(filter: &mut impl FnMut(f32) -> f32, frequency: f32) -> f32 {
+    let samples: Vec<f32> = (0..SAMPLE_RATE)
+        .map(|n| filter((2.0 * PI * frequency * n as f32 / SAMPLE_RATE as f32).sin()))
+        .collect();
+    // skip the first half second while the filter settles
+    samples[SAMPLE_RATE as usize / 2..]
+        .iter()
+        .fold(0.0, |peak, sample| f32::max(peak, sample.abs()))
+}
+
+#[test]
+fn high_pass_removes_dc() {
+    let mut filter = HighPass::new(HIGH_PASS_1_HZ, SAMPLE_RATE);
+    let mut output = 0.0;
+    for _ in 0..SAMPLE_RATE {
+        output = filter.process(1.0);
+    }
+    assert!(output.abs() < 1e-3);
+}
+
+#[test]
+fn low_pass_keeps_dc() {
+    let mut filter = LowPass::new(LOW_PASS_HZ, SAMPLE_RATE);
+    let mut output = 0.0;
+    for _ in 0..SAMPLE_RATE {
+        output = filter.process(1.0);
+    }
+    assert!((output - 1.0).abs() < 1e-3);
+}
+
+#[test]
+fn cutoff_halves_the_power() {
+    let mut high_pass = HighPass::new(HIGH_PASS_2_HZ, SAMPLE_RATE);
+    let amplitude = tone_amplitude(&mut |x| high_pass.process(x), HIGH_PASS_2_HZ);
+    assert!((amplitude - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.05);
+
+    let mut low_pass = LowPass::new(LOW_PASS_HZ, SAMPLE_RATE);
+    let amplitude = tone_amplitude(&mut |x| low_pass.process(x), 1000.0);
+    assert!(amplitude > 0.95);
+}
+
+#[test]
+fn chain_passes_the_midrange_only() {
+    let mut chain = FilterChain::nes(SAMPLE_RATE);
+    let mid = tone_amplitude(&mut |x| chain.process(x), 2000.0);
+    let mut chain = FilterChain::nes(SAMPLE_RATE);
+    let low = tone_amplitude(&mut |x| chain.process(x), 40.0);
+
+    assert!(mid > 0.9);
+    assert!(low < 0.2);
+}
@@ -0,0 +1,109 @@
+pub const NUM_OF_CHANNELS: usize = 5;
+
+// every sum of two 4 bit pulse levels, and of 3 * triangle + 2 * noise + dmc
+const PULSE_TABLE_SIZE: usize = 31;
+const TND_TABLE_SIZE: usize = 203;
+const TRIANGLE_WEIGHT: f32 = 3.0;
+const NOISE_WEIGHT: f32 = 2.0;
+
+lazy_static! {
+    static ref PULSE_TABLE: [f32; PULSE_TABLE_SIZE] =
+        std::array::from_fn(|n| mixer_level(95.52, 8128.0, n));
+    static ref TND_TABLE: [f32; TND_TABLE_SIZE] =
+        std::array::from_fn(|n| mixer_level(163.67, 24329.0, n));
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Channel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+}
+
+impl Channel {
+    pub fn from_name(name: &str) -> Option<Channel> {
+        match name {
+            "pulse1" => Some(Channel::Pulse1),
+            "pulse2" => Some(Channel::Pulse2),
+            "triangle" => Some(Channel::Triangle),
+            "noise" => Some(Channel::Noise),
+            "dmc" => Some(Channel::Dmc),
+            _ => None,
+        }
+    }
+}
+
+// the DAC levels are summed nonlinearly, so one loud channel compresses the others
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mixer {
+    volumes: [f32; NUM_OF_CHANNELS],
+    muted: [bool; NUM_OF_CHANNELS],
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Mixer {
+            volumes: [1.0; NUM_OF_CHANNELS],
+            muted: [false; NUM_OF_CHANNELS],
+        }
+    }
+}
+
+impl Mixer {
+    pub fn set_volume(&mut self, channel: Channel, volume: f32) {
+        self.volumes[channel as usize] = volume.max(0.0);
+    }
+
+    pub fn get_volume(&self, channel: Channel) -> f32 {
+        self.volumes[channel as usize]
+    }
+
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        self.muted[channel as usize] = muted;
+    }
+
+    pub fn is_muted(&self, channel: Channel) -> bool {
+        self.muted[channel as usize]
+    }
+
+    // levels in Channel order, straight from the channel outputs
+    pub fn mix(&self, levels: [u8; NUM_OF_CHANNELS]) -> f32 {
+        let level = |channel: Channel| {
+            if self.is_muted(channel) {
+                0.0
+            } else {
+                levels[channel as usize] as f32 * self.get_volume(channel)
+            }
+        };
+
+        let pulse = level(Channel::Pulse1) + level(Channel::Pulse2);
+        let tnd = TRIANGLE_WEIGHT * level(Channel::Triangle)
+            + NOISE_WEIGHT * level(Channel::Noise)
+            + level(Channel::Dmc);
+
+        lookup(&PULSE_TABLE[..], pulse) + lookup(&TND_TABLE[..], tnd)
+    }
+}
+
+fn mixer_level(scale: f32, divisor: f32, n: usize) -> f32 {
+    if n == 0 {
+        0.0
+    } else {
+        scale / (divisor / n as f32 + 100.0)
+    }
+}
+
+// scaled volumes fall between entries, full volume hits them exactly
+fn lookup(table: &[f32], index: f32) -> f32 {
+    let index = index.clamp(0.0, (table.len() - 1) as f32);
+    let low = index.floor() as usize;
+    let high = (low + 1).min(table.len() - 1);
+    let fraction = index - low as f32;
+
+    table[low] + (table[high] - table[low]) * fraction
+}
+
+#[cfg(test)]
+mod test;
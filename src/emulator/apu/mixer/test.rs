@@ -0,0 +1,72 @@
+use super::*;
+
+const MAX_LEVEL: u8 = 15;
+const MAX_DMC_LEVEL: u8 = 127;
+
+fn solo(channel: Channel, level: u8) -> [u8; NUM_OF_CHANNELS] {
+    let mut levels = [0; NUM_OF_CHANNELS];
+    levels[channel as usize] = level;
+    levels
+}
+
+fn assert_close(value: f32, expected: f32) {
+    assert!((value - expected).abs() < 1e-5, "{value} vs {expected}");
+}
+
+#[test]
+fn silence_mixes_to_zero() {
+    assert_eq!(Mixer::default().mix([0; NUM_OF_CHANNELS]), 0.0);
+}
+
+#[test]
+fn tables_match_the_reference_formula() {
+    assert_close(PULSE_TABLE[30], 95.52 / (8128.0 / 30.0 + 100.0));
+    assert_close(TND_TABLE[202], 163.67 / (24329.0 / 202.0 + 100.0));
+
+    let mixer = Mixer::default();
+    let levels = [MAX_LEVEL, MAX_LEVEL, MAX_LEVEL, MAX_LEVEL, MAX_DMC_LEVEL];
+    assert_close(mixer.mix(levels), PULSE_TABLE[30] + TND_TABLE[202]);
+}
+
+#[test]
+fn pulses_compress_when_summed() {
+    let mixer = Mixer::default();
+    let one = mixer.mix(solo(Channel::Pulse1, MAX_LEVEL));
+    let both = mixer.mix([MAX_LEVEL, MAX_LEVEL, 0, 0, 0]);
+    assert!(both < 2.0 * one);
+}
+
+#[test]
+fn dmc_is_weighted_against_triangle_and_noise() {
+    let mixer = Mixer::default();
+    assert_close(
+        mixer.mix(solo(Channel::Triangle, 10)),
+        mixer.mix(solo(Channel::Dmc, 30)),
+    );
+    assert_close(
+        mixer.mix(solo(Channel::Noise, 10)),
+        mixer.mix(solo(Channel::Dmc, 20)),
+    );
+}
+
+#[test]
+fn muted_channels_are_silent() {
+    let mut mixer = Mixer::default();
+    mixer.set_muted(Channel::Noise, true);
+    assert_eq!(mixer.mix(solo(Channel::Noise, MAX_LEVEL)), 0.0);
+    assert!(mixer.mix(solo(Channel::Pulse2, MAX_LEVEL)) > 0.0);
+}
+
+#[test]
+fn volume_scales_the_channel_level() {
+    let mut mixer = Mixer::default();
+    mixer.set_volume(Channel::Pulse1, 0.5);
+    assert_close(
+        mixer.mix(solo(Channel::Pulse1, 10)),
+        Mixer::default().mix(solo(Channel::Pulse1, 5)),
+    );
+
+    // odd levels land between two table entries
+    let between = mixer.mix(solo(Channel::Pulse1, 3));
+    assert_close(between, (PULSE_TABLE[1] + PULSE_TABLE[2]) / 2.0);
+}
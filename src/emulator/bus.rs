@@ -1,13 +1,14 @@
-use core::panic;
-
 use super::apu::Apu;
+use super::cdl::{self, CodeDataLog, PRG_CODE, PRG_DATA, PRG_INDIRECT_DATA, PRG_PCM_DATA};
 use super::joypad::JoyPad;
 use super::memory::MemAccess;
 use super::ppu::Ppu;
+use super::recorder::Recorder;
 use super::rom::Rom;
 
 pub struct Bus<'call> {
     cpu_vram: [u8; VRAM_SIZE],
+    prg_ram: [u8; PRG_RAM_SIZE],
     prg_rom: Vec<u8>,
     ppu: Ppu,
     apu: Apu,
@@ -16,7 +17,12 @@ pub struct Bus<'call> {
     gameloop_callback: Box<dyn FnMut(&Ppu, &mut JoyPad) + 'call>,
 
     apu_sample_buffer: Vec<f32>,
-    apu_time_accumulator: f64,
+    recorder: Option<Recorder>,
+
+    prg_log: Option<Vec<u8>>,
+    instruction_addr: u16,
+    instruction_len: u16,
+    instruction_indirect: bool,
 }
 
 const VRAM_SIZE: usize = 2048;
@@ -25,6 +31,9 @@ const RAM_MIRRORS_END: u16 = 0x1FFF;
 const PPU_REGISTERS: u16 = 0x2000;
 const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
 const MASK_11_BITS: u16 = 0b0000_0111_1111_1111;
+const PRG_RAM_SIZE: usize = 0x2000;
+const PRG_RAM_START_ADDR: u16 = 0x6000;
+const PRG_RAM_END_ADDR: u16 = 0x7FFF;
 const PRG_ROM_START_ADDR: u16 = 0x8000;
 const PRG_ROM_END_ADDR: u16 = 0xFFFF;
 const PRG_ROM_PAGE_SIZE: u16 = 0x4000;
@@ -48,12 +57,12 @@ const APU_STATUS: u16 = 0x4015;
 const APU_FRAME_COUNTER: u16 = 0x4017;
 
 const APU_SAMPLES_BUFFER_SIZE: usize = 4096;
-const APU_CYCLES_PER_SAMPLE: f64 = 1789773.0 / 44100.0;
 
 const JOYPAD_ADDR: u16 = 0x4016;
 const JOYPAD_2_ADDR: u16 = 0x4017;
 
 const PPU_CPU_CYCLES_RATIO: u8 = 3;
+const UNPEEKABLE_VALUE: u8 = 0xFF;
 
 const PAGE_SIZE: usize = 256;
 const BYTE_SIZE: u8 = 8;
@@ -65,14 +74,19 @@ impl<'a> Bus<'a> {
     {
         Bus {
             cpu_vram: [0; VRAM_SIZE],
+            prg_ram: [0; PRG_RAM_SIZE],
             prg_rom: rom.take_prg_rom(),
-            ppu: Ppu::new(rom.take_chr_rom(), rom.get_mirroring()),
+            ppu: Ppu::new(rom.take_chr_rom(), rom.nametables()),
             apu: Apu::new(),
             joy_pad: JoyPad::new(),
             cycles: 0,
             gameloop_callback: Box::from(gameloop_cb),
             apu_sample_buffer: Vec::with_capacity(APU_SAMPLES_BUFFER_SIZE),
-            apu_time_accumulator: 0.0,
+            recorder: None,
+            prg_log: None,
+            instruction_addr: 0,
+            instruction_len: 0,
+            instruction_indirect: false,
         }
     }
 
@@ -81,9 +95,16 @@ impl<'a> Bus<'a> {
 
         for _ in 0..(cycles * PPU_CPU_CYCLES_RATIO as u16) {
             let nmi_before = self.ppu.is_nmi_interrupt();
+            let frame_before = self.ppu.get_frame_count();
             self.ppu.tick();
             let nmi_after = self.ppu.is_nmi_interrupt();
 
+            // a frame ends at vblank whether or not the game asked for an NMI
+            if let Some(recorder) = self.recorder.as_mut()
+                && self.ppu.get_frame_count() != frame_before
+            {
+                recorder.push_frame(&self.ppu.screen);
+            }
             if !nmi_before && nmi_after {
                 (self.gameloop_callback)(&self.ppu, &mut self.joy_pad);
             }
@@ -95,15 +116,15 @@ impl<'a> Bus<'a> {
             if self.apu.needs_dmc_sample() {
                 let addr = self.apu.get_dmc_addr();
                 let val = self.mem_read(addr);
+                self.log_prg(addr, PRG_PCM_DATA);
                 self.apu.set_dmc_sample(val);
             }
 
-            self.apu_time_accumulator += 1.0;
-            while self.apu_time_accumulator >= APU_CYCLES_PER_SAMPLE {
-                self.apu_time_accumulator -= APU_CYCLES_PER_SAMPLE;
-
-                let sample = self.apu.get_audio_sample();
+            while let Some(sample) = self.apu.next_sample() {
                 self.apu_sample_buffer.push(sample);
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.push_sample(sample);
+                }
             }
         }
     }
@@ -112,12 +133,91 @@ impl<'a> Bus<'a> {
         self.ppu.take_nmi_interrupt()
     }
 
-    fn read_prg_rom(&self, addr: u16) -> u8 {
+    fn prg_rom_offset(&self, addr: u16) -> Option<usize> {
+        if addr < PRG_ROM_START_ADDR {
+            return None;
+        }
+
         let mut rom_addr = addr - PRG_ROM_START_ADDR;
         if self.prg_rom.len() == PRG_ROM_PAGE_SIZE as usize {
             rom_addr &= PRG_ROM_PAGE_SIZE - 1;
         }
-        self.prg_rom[rom_addr as usize]
+        Some(rom_addr as usize)
+    }
+
+    fn read_prg_rom(&self, addr: u16) -> u8 {
+        self.prg_rom[self.prg_rom_offset(addr).unwrap()]
+    }
+
+    pub fn enable_code_data_log(&mut self, log: CodeDataLog) {
+        self.prg_log = Some(log.prg);
+        self.ppu.enable_chr_log(log.chr);
+    }
+
+    pub fn is_code_data_logging(&self) -> bool {
+        self.prg_log.is_some()
+    }
+
+    pub fn get_code_data_log(&self) -> Option<CodeDataLog> {
+        Some(CodeDataLog {
+            prg: self.prg_log.clone()?,
+            chr: self.ppu.get_chr_log().unwrap_or_default().to_vec(),
+        })
+    }
+
+    // marks the opcode and operand bytes as code, so their fetches are not logged as data
+    pub fn log_instruction(&mut self, addr: u16, len: u16, indirect: bool) {
+        self.instruction_addr = addr;
+        self.instruction_len = len;
+        self.instruction_indirect = indirect;
+
+        for i in 0..len {
+            self.log_prg(addr.wrapping_add(i), PRG_CODE);
+        }
+    }
+
+    pub fn log_prg(&mut self, addr: u16, flags: u8) {
+        let Some(offset) = self.prg_rom_offset(addr) else {
+            return;
+        };
+
+        if let Some(log) = self.prg_log.as_mut() {
+            log[offset] |= cdl::prg_flags(addr, flags);
+        }
+    }
+
+    fn log_prg_data(&mut self, addr: u16) {
+        if self.prg_log.is_none() || addr.wrapping_sub(self.instruction_addr) < self.instruction_len
+        {
+            return;
+        }
+
+        let mut flags = PRG_DATA;
+        if self.instruction_indirect {
+            flags |= PRG_INDIRECT_DATA;
+        }
+        self.log_prg(addr, flags);
+    }
+
+    pub fn prg_bank(&self, addr: u16) -> Option<usize> {
+        self.prg_rom_offset(addr)
+            .map(|offset| offset / PRG_ROM_PAGE_SIZE as usize)
+    }
+
+    // reads memory without the side effects of reading I/O registers
+    pub fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            RAM..=RAM_MIRRORS_END => self.cpu_vram[(addr & MASK_11_BITS) as usize],
+            PRG_RAM_START_ADDR..=PRG_RAM_END_ADDR => {
+                self.prg_ram[(addr - PRG_RAM_START_ADDR) as usize]
+            }
+            PRG_ROM_START_ADDR..=PRG_ROM_END_ADDR => self.read_prg_rom(addr),
+            _ => UNPEEKABLE_VALUE,
+        }
+    }
+
+    pub fn peek_u16(&self, addr: u16) -> u16 {
+        u16::from_le_bytes([self.peek(addr), self.peek(addr.wrapping_add(1))])
     }
 
     pub fn get_audio_samples(&mut self) -> Vec<f32> {
@@ -131,10 +231,52 @@ impl<'a> Bus<'a> {
         self.apu_sample_buffer.len()
     }
 
+    pub fn start_recording(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    // finishes the files, not recording is not an error
+    pub fn stop_recording(&mut self) -> std::io::Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn get_recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
     pub fn get_cycles(&self) -> usize {
         self.cycles
     }
 
+    pub fn get_ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    pub fn get_ppu_mut(&mut self) -> &mut Ppu {
+        &mut self.ppu
+    }
+
+    pub fn get_apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    pub fn get_apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    // only the screenshot tests press buttons
+    #[cfg(test)]
+    pub fn get_joypad_mut(&mut self) -> &mut JoyPad {
+        &mut self.joy_pad
+    }
+
     pub fn poll_irq_status(&self) -> bool {
         self.apu.get_irq()
     }
@@ -147,11 +289,10 @@ impl MemAccess for Bus<'_> {
                 let mirror_down_addr = addr & MASK_11_BITS;
                 self.cpu_vram[mirror_down_addr as usize]
             }
-            PPU_CTRL_REG | PPU_MASK_REG | PPU_OAM_ADDR_REG | PPU_SCROLL_REG | PPU_ADDR_REG
-            | PPU_OAM_DMA_REG => {
-                // panic!("Attempt to read from write only PPU address {:x}", addr)
-                0
+            PPU_CTRL_REG | PPU_MASK_REG | PPU_OAM_ADDR_REG | PPU_SCROLL_REG | PPU_ADDR_REG => {
+                self.ppu.read_open_bus()
             }
+            PPU_OAM_DMA_REG => 0,
             PPU_STATUS_REG => self.ppu.read_status(),
             PPU_DATA_REG => self.ppu.read_data(),
             PPU_OAM_DATA_REG => self.ppu.read_oam_data(),
@@ -163,7 +304,13 @@ impl MemAccess for Bus<'_> {
 
             JOYPAD_ADDR => self.joy_pad.read(),
             JOYPAD_2_ADDR => 0, // TODO: implement second joystick
-            PRG_ROM_START_ADDR..=PRG_ROM_END_ADDR => self.read_prg_rom(addr),
+            PRG_RAM_START_ADDR..=PRG_RAM_END_ADDR => {
+                self.prg_ram[(addr - PRG_RAM_START_ADDR) as usize]
+            }
+            PRG_ROM_START_ADDR..=PRG_ROM_END_ADDR => {
+                self.log_prg_data(addr);
+                self.read_prg_rom(addr)
+            }
             _ => {
                 println!("memory read not supported yet at: {:x}", addr);
                 0
@@ -172,6 +319,14 @@ impl MemAccess for Bus<'_> {
     }
 
     fn mem_write(&mut self, addr: u16, data: u8) {
+        // mirrored PPU registers are logged when they come back mirrored down
+        if let PPU_CTRL_REG..=PPU_DATA_REG
+        | PPU_OAM_DMA_REG
+        | PRG_ROM_START_ADDR..=PRG_ROM_END_ADDR = addr
+        {
+            self.ppu.log_write(addr, data);
+        }
+
         match addr {
             RAM..=RAM_MIRRORS_END => {
                 let mirror_down_addr = addr & MASK_11_BITS;
@@ -179,7 +334,7 @@ impl MemAccess for Bus<'_> {
             }
             PPU_CTRL_REG => self.ppu.write_to_ctrl(data),
             PPU_MASK_REG => self.ppu.write_to_mask(data),
-            PPU_STATUS_REG => panic!("attempt to write to PPU status reg"),
+            PPU_STATUS_REG => self.ppu.write_to_status(data),
             PPU_OAM_ADDR_REG => self.ppu.write_to_oam_addr(data),
             PPU_OAM_DATA_REG => self.ppu.write_to_oam_data(data),
             PPU_SCROLL_REG => self.ppu.write_to_scroll(data),
@@ -210,9 +365,11 @@ impl MemAccess for Bus<'_> {
                 self.apu.write_register(addr, data)
             }
             JOYPAD_ADDR => self.joy_pad.write(data),
-            PRG_ROM_START_ADDR..=PRG_ROM_END_ADDR => {
-                panic!("Attempt to write to Cartridge ROM at: {:x}", addr);
+            PRG_RAM_START_ADDR..=PRG_RAM_END_ADDR => {
+                self.prg_ram[(addr - PRG_RAM_START_ADDR) as usize] = data;
             }
+            // NROM has no registers, the write only shows up in the event log
+            PRG_ROM_START_ADDR..=PRG_ROM_END_ADDR => {}
             _ => {
                 println!("memory write not supported yet at: {:x}", addr);
             }
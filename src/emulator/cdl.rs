@@ -0,0 +1,73 @@
+use std::fs;
+use std::io;
+
+// PRG flags, one byte per PRG ROM byte: xPdcAADC
+pub const PRG_CODE: u8 = 0b0000_0001;
+pub const PRG_DATA: u8 = 0b0000_0010;
+pub const PRG_INDIRECT_CODE: u8 = 0b0001_0000;
+pub const PRG_INDIRECT_DATA: u8 = 0b0010_0000;
+pub const PRG_PCM_DATA: u8 = 0b0100_0000;
+const PRG_BANK_SHIFT: u8 = 2;
+const CPU_WINDOW_SHIFT: u16 = 13;
+const CPU_WINDOW_MASK: u16 = 0b11;
+
+// CHR flags, one byte per CHR ROM byte: xxxxxxRD
+pub const CHR_RENDERED: u8 = 0b0000_0001;
+pub const CHR_READ: u8 = 0b0000_0010;
+
+pub struct CodeDataLog {
+    pub prg: Vec<u8>,
+    pub chr: Vec<u8>,
+}
+
+impl CodeDataLog {
+    pub fn new(prg_size: usize, chr_size: usize) -> Self {
+        CodeDataLog {
+            prg: vec![0; prg_size],
+            chr: vec![0; chr_size],
+        }
+    }
+
+    pub fn from_bytes(data: &[u8], prg_size: usize, chr_size: usize) -> Result<Self, String> {
+        if data.len() != prg_size + chr_size {
+            return Err(format!(
+                "CDL size {} does not match rom size {}",
+                data.len(),
+                prg_size + chr_size
+            ));
+        }
+
+        Ok(CodeDataLog {
+            prg: data[..prg_size].to_vec(),
+            chr: data[prg_size..].to_vec(),
+        })
+    }
+
+    // only a missing file starts a fresh log, anything else would overwrite the coverage
+    pub fn load_or_new(path: &str, prg_size: usize, chr_size: usize) -> Result<Self, String> {
+        match fs::read(path) {
+            Ok(data) => {
+                Self::from_bytes(&data, prg_size, chr_size).map_err(|err| format!("{path}: {err}"))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::new(prg_size, chr_size)),
+            Err(err) => Err(format!("{path}: {err}")),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.prg.as_slice(), self.chr.as_slice()].concat()
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+// the AA bits record which 8KB CPU window ($8000/$A000/$C000/$E000) the byte was seen through
+pub fn prg_flags(cpu_addr: u16, flags: u8) -> u8 {
+    let window = (cpu_addr >> CPU_WINDOW_SHIFT) & CPU_WINDOW_MASK;
+    flags | ((window as u8) << PRG_BANK_SHIFT)
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,45 @@
+use super::*;
+use crate::emulator::test_helpers::{temp_dir, temp_file};
+
+const CDL_DIR: &str = "cdl";
+const PRG_SIZE: usize = 0x10;
+const CHR_SIZE: usize = 0x08;
+
+fn cdl_path(name: &str) -> String {
+    temp_file(CDL_DIR, &format!("{name}.cdl"))
+}
+
+#[test]
+fn missing_log_starts_fresh() {
+    let path = cdl_path("missing");
+
+    let log = CodeDataLog::load_or_new(&path, PRG_SIZE, CHR_SIZE).unwrap();
+    assert_eq!(log.to_bytes(), vec![0; PRG_SIZE + CHR_SIZE]);
+}
+
+#[test]
+fn existing_log_is_continued() {
+    let path = cdl_path("existing");
+    let mut log = CodeDataLog::new(PRG_SIZE, CHR_SIZE);
+    log.prg[1] = PRG_CODE;
+    log.save(&path).unwrap();
+
+    let loaded = CodeDataLog::load_or_new(&path, PRG_SIZE, CHR_SIZE).unwrap();
+    assert_eq!(loaded.prg[1], PRG_CODE);
+}
+
+#[test]
+fn mismatched_log_is_an_error() {
+    let path = cdl_path("mismatched");
+    fs::write(&path, [PRG_CODE; 3]).unwrap();
+
+    assert!(CodeDataLog::load_or_new(&path, PRG_SIZE, CHR_SIZE).is_err());
+    // the user's file is left alone
+    assert_eq!(fs::read(&path).unwrap(), [PRG_CODE; 3]);
+}
+
+#[test]
+fn unreadable_log_is_an_error() {
+    // a directory can't be read as a file, but it exists
+    assert!(CodeDataLog::load_or_new(&temp_dir(CDL_DIR), PRG_SIZE, CHR_SIZE).is_err());
+}
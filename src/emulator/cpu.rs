@@ -3,10 +3,17 @@ mod status;
 pub mod trace;
 
 use core::panic;
+use std::io;
 
+use super::apu::Apu;
 use super::bus::Bus;
+use super::cdl::{CodeDataLog, PRG_INDIRECT_CODE};
+#[cfg(test)]
+use super::joypad::JoyPad;
 use super::memory::MemAccess;
-use opcode::OPCODE_TABLE;
+use super::ppu::Ppu;
+use super::recorder::Recorder;
+use opcode::{NO_PAGE_PENALTY, OPCODE_TABLE};
 use status::*;
 
 pub struct CPU6502<'a> {
@@ -18,9 +25,16 @@ pub struct CPU6502<'a> {
     indx_reg_y: u8,
     page_crossed: bool,
     branch_taken: bool,
+    last_interrupt: Option<Interrupt>,
     bus: Bus<'a>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    Nmi,
+    Irq,
+}
+
 enum RegName {
     X,
     Y,
@@ -53,6 +67,8 @@ const STACK_ADDR: u16 = 0x0100; // 0x0100 - 0x01FF
 const PAGE_SIZE: u8 = 0xFF;
 
 const STACK_POINTER_START_INDX: u8 = 0xFD;
+const RESET_CYCLES: u16 = 7;
+const JMP_INDIRECT_OPCODE: u8 = 0x6C;
 
 const BIT_0: u8 = 0b0000_0001;
 const BIT_7: u8 = 0b1000_0000;
@@ -70,6 +86,7 @@ impl<'a> CPU6502<'a> {
             indx_reg_y: 0,
             page_crossed: false,
             branch_taken: false,
+            last_interrupt: None,
             bus,
         }
     }
@@ -92,21 +109,81 @@ impl<'a> CPU6502<'a> {
         let mut break_status: bool = false;
 
         while !break_status {
-            if let Some(_nmi) = self.bus.poll_nmi_status() {
-                self.interrupt_nmi();
-            } else if self.bus.poll_irq_status() {
-                self.interrupt_irq();
-            }
+            self.handle_interrupts();
 
             callback(self);
 
-            let op_code = self.bus.mem_read(self.program_counter);
-            self.program_counter += 1;
+            break_status = self.execute_instruction();
+        }
+    }
+
+    pub fn step(&mut self) -> bool {
+        self.handle_interrupts();
+        self.execute_instruction()
+    }
 
-            break_status = self.op_code_instraction(op_code);
+    fn handle_interrupts(&mut self) {
+        self.last_interrupt = None;
+
+        if let Some(_nmi) = self.bus.poll_nmi_status() {
+            self.interrupt_nmi();
+        } else if self.bus.poll_irq_status() {
+            self.interrupt_irq();
         }
     }
 
+    fn execute_instruction(&mut self) -> bool {
+        if self.bus.is_code_data_logging() {
+            self.log_instruction();
+        }
+
+        let op_code = self.bus.mem_read(self.program_counter);
+        self.program_counter += 1;
+
+        let is_break = self.op_code_instraction(op_code);
+
+        if (op_code == JMP_INDIRECT_OPCODE) && self.bus.is_code_data_logging() {
+            self.bus.log_prg(self.program_counter, PRG_INDIRECT_CODE);
+        }
+
+        is_break
+    }
+
+    fn log_instruction(&mut self) {
+        use AddressingMode::*;
+
+        let opcode = &OPCODE_TABLE[self.bus.peek(self.program_counter) as usize];
+        let len = 1 + Self::num_of_address_mode_bytes(&opcode.addr_mode);
+        let indirect = matches!(opcode.addr_mode, IndexedIndirectX | IndirectIndexedY);
+
+        self.bus
+            .log_instruction(self.program_counter, len, indirect);
+    }
+
+    pub fn enable_code_data_log(&mut self, log: CodeDataLog) {
+        self.bus.enable_code_data_log(log);
+    }
+
+    pub fn get_code_data_log(&self) -> Option<CodeDataLog> {
+        self.bus.get_code_data_log()
+    }
+
+    pub fn start_recording(&mut self, recorder: Recorder) {
+        self.bus.start_recording(recorder);
+    }
+
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        self.bus.stop_recording()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.bus.is_recording()
+    }
+
+    pub fn get_recorder(&self) -> Option<&Recorder> {
+        self.bus.get_recorder()
+    }
+
     pub fn reset(&mut self) {
         self.accumulator = 0;
         self.indx_reg_x = 0;
@@ -114,6 +191,7 @@ impl<'a> CPU6502<'a> {
         self.status_reg = StatusReg::new();
         self.stack_pointer = STACK_POINTER_START_INDX;
         self.program_counter = self.bus.mem_read_u16(RESET_LOCATION);
+        self.bus.tick(RESET_CYCLES);
     }
 
     pub fn load_and_run(&mut self, program: Vec<u8>) {
@@ -142,11 +220,34 @@ impl<'a> CPU6502<'a> {
         self.bus.get_cycles()
     }
 
+    pub fn get_ppu(&self) -> &Ppu {
+        self.bus.get_ppu()
+    }
+
+    pub fn get_ppu_mut(&mut self) -> &mut Ppu {
+        self.bus.get_ppu_mut()
+    }
+
+    pub fn get_apu(&self) -> &Apu {
+        self.bus.get_apu()
+    }
+
+    pub fn get_apu_mut(&mut self) -> &mut Apu {
+        self.bus.get_apu_mut()
+    }
+
+    // only the screenshot tests press buttons
+    #[cfg(test)]
+    pub fn get_joypad_mut(&mut self) -> &mut JoyPad {
+        self.bus.get_joypad_mut()
+    }
+
     fn interrupt_nmi(&mut self) {
         self.common_interrupt();
 
         let handler_addr = self.bus.mem_read_u16(NON_MASKABLE_INTER_HNDLER_ADDR);
         self.program_counter = handler_addr;
+        self.last_interrupt = Some(Interrupt::Nmi);
         self.bus.tick(7);
     }
 
@@ -156,6 +257,7 @@ impl<'a> CPU6502<'a> {
 
             let handler_addr = self.bus.mem_read_u16(BRK_INTR_HANDLER_ADDR);
             self.program_counter = handler_addr;
+            self.last_interrupt = Some(Interrupt::Irq);
 
             self.bus.tick(7);
         }
@@ -521,13 +623,14 @@ impl<'a> CPU6502<'a> {
         if cond {
             let addr = self.get_operand_addr(mode);
             let val = self.bus.mem_read(addr) as i8;
-            let sum = self
+            let next = self
                 .program_counter
-                .wrapping_add(Self::num_of_address_mode_bytes(mode))
-                .wrapping_add(val as u16);
+                .wrapping_add(Self::num_of_address_mode_bytes(mode));
+            let sum = next.wrapping_add(val as u16);
 
+            // the extra cycle is for leaving the page of the next instruction
             self.branch_taken = true;
-            self.page_crossed = Self::page_cross(self.program_counter, sum);
+            self.page_crossed = Self::page_cross(next, sum);
 
             self.program_counter = sum;
         }
@@ -629,7 +732,12 @@ impl<'a> CPU6502<'a> {
     }
 
     // other ops
-    fn nop(&mut self, _mode: &AddressingMode) {}
+    // the unofficial absolute,X NOPs still read, so they pay for a page cross
+    fn nop(&mut self, mode: &AddressingMode) {
+        if let AddressingMode::AbsoluteX = mode {
+            self.get_operand_addr(mode);
+        }
+    }
 
     // unofficial opcodes
     fn aso(&mut self, mode: &AddressingMode) {
@@ -873,6 +981,9 @@ impl<'a> CPU6502<'a> {
         self.branch_taken = false;
 
         (opcode.instraction)(self, &opcode.addr_mode);
+        if NO_PAGE_PENALTY.contains(&op_code) {
+            self.page_crossed = false;
+        }
 
         self.bus
             .tick((opcode.cycles + self.page_crossed as u8 + self.branch_taken as u8) as u16);
@@ -8,6 +8,12 @@ pub struct OpCode {
 
 pub const NUM_OF_OPCODES: usize = 256;
 
+// indexed stores and read-modify-writes always spend the fix-up cycle, it is in their base cycles
+pub const NO_PAGE_PENALTY: [u8; 32] = [
+    0x13, 0x1B, 0x1E, 0x1F, 0x33, 0x3B, 0x3E, 0x3F, 0x53, 0x5B, 0x5E, 0x5F, 0x73, 0x7B, 0x7E, 0x7F,
+    0x91, 0x93, 0x99, 0x9B, 0x9C, 0x9D, 0x9E, 0x9F, 0xD3, 0xDB, 0xDE, 0xDF, 0xF3, 0xFB, 0xFE, 0xFF,
+];
+
 pub const OPCODE_TABLE: [OpCode; NUM_OF_OPCODES] = [
     OpCode {
         instraction: |cpu: &mut CPU6502, mode: &AddressingMode| cpu.brk(mode),
@@ -1,8 +1,16 @@
 use crate::emulator::rom::Rom;
 
 use super::*;
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use trace::{TraceFilter, Tracer, trace};
 
 fn test_rom(program: &[u8]) -> Rom {
+    test_rom_with_nmi(program, 0x0000)
+}
+
+fn test_rom_with_nmi(program: &[u8], nmi_addr: u16) -> Rom {
     const HEADER_SIZE: usize = 16;
     const PRG_PAGE_SIZE: usize = 0x4000;
     const CHR_PAGE_SIZE: usize = 0x2000;
@@ -22,6 +30,7 @@ fn test_rom(program: &[u8]) -> Rom {
     // set rom start address
     prg_rom[PRG_PAGE_SIZE - 3] = 0x80;
     prg_rom[PRG_PAGE_SIZE - 4] = 0x00;
+    prg_rom[PRG_PAGE_SIZE - 6..PRG_PAGE_SIZE - 4].copy_from_slice(&nmi_addr.to_le_bytes());
     prg_rom[..program.len()].copy_from_slice(program);
 
     let mut rom = Vec::with_capacity(HEADER_SIZE + PRG_PAGE_SIZE + CHR_PAGE_SIZE);
@@ -181,6 +190,323 @@ fn test_sta_0x85_store_accumulatore() {
     assert_eq!(cpu.bus.mem_read(0x10), 123);
 }
 
+// cycles each instruction took, keyed by its address
+fn instruction_cycles(program: &[u8]) -> Vec<(u16, usize)> {
+    let bus = Bus::new(test_rom(program), |_, _| {});
+    let mut cpu = CPU6502::new(bus);
+    cpu.reset();
+
+    let mut starts = Vec::new();
+    cpu.run_with_callback(|cpu| starts.push((cpu.program_counter, cpu.get_cycles_count())));
+    starts
+        .windows(2)
+        .map(|pair| (pair[0].0, pair[1].1 - pair[0].1))
+        .collect()
+}
+
+#[test]
+fn only_reads_pay_for_a_page_cross() {
+    #[rustfmt::skip]
+    let program = [
+        0xA2, 0x01,       // $8000 LDX #$01
+        0xBD, 0xFF, 0x02, // $8002 LDA $02FF,X
+        0xBD, 0x00, 0x02, // $8005 LDA $0200,X
+        0x9D, 0xFF, 0x02, // $8008 STA $02FF,X
+        0xFE, 0xFF, 0x02, // $800B INC $02FF,X
+        0x1F, 0xFF, 0x02, // $800E SLO $02FF,X
+        0x1C, 0xFF, 0x02, // $8011 NOP $02FF,X
+        0x00,             // $8014 BRK
+    ];
+
+    assert_eq!(
+        instruction_cycles(&program),
+        [
+            (0x8000, 2),
+            (0x8002, 5),
+            (0x8005, 4),
+            (0x8008, 5),
+            (0x800B, 7),
+            (0x800E, 7),
+            (0x8011, 5),
+        ]
+    );
+}
+
+#[test]
+fn branches_pay_for_leaving_the_next_instructions_page() {
+    let mut program = vec![0x00; 0x104];
+    // $8000 JMP $80FD
+    program[..3].copy_from_slice(&[0x4C, 0xFD, 0x80]);
+    // $80FD CLC, $80FE BCC to $8101, on the page of the next instruction at $8100
+    program[0xFD..0x100].copy_from_slice(&[0x18, 0x90, 0x01]);
+    // $8101 BCC back to $80F3, off the page of $8103, which holds BRK
+    program[0x101..0x103].copy_from_slice(&[0x90, 0xF0]);
+
+    assert_eq!(
+        instruction_cycles(&program),
+        [(0x8000, 3), (0x80FD, 2), (0x80FE, 3), (0x8101, 4)]
+    );
+}
+
+const NESTEST_ROM: &str = "roms/tests/nestest.nes";
+const NESTEST_LOG: &str = "roms/tests/nestest.log";
+const NESTEST_AUTOMATION_START: u16 = 0xC000;
+const NESTEST_CONTEXT_LINES: usize = 5;
+const NESTEST_MAX_EXTRA_LINES: usize = 100;
+
+fn nestest_cpu<'a>() -> CPU6502<'a> {
+    let program = std::fs::read(NESTEST_ROM).unwrap();
+    let rom = Rom::new(&program).unwrap();
+    let bus = Bus::new(rom, |_, _| {});
+
+    let mut cpu = CPU6502::new(bus);
+    cpu.reset();
+    cpu.program_counter = NESTEST_AUTOMATION_START;
+    cpu
+}
+
+fn nestest_divergence_report(
+    line: usize,
+    history: &[String],
+    expected: &str,
+    actual: &str,
+) -> String {
+    let column = expected
+        .chars()
+        .zip(actual.chars())
+        .position(|(e, a)| e != a)
+        .unwrap_or(expected.len().min(actual.len()));
+
+    let mut report = format!("nestest diverged from {NESTEST_LOG} at line {line}:\n");
+    for prev in history {
+        report.push_str(&format!("           {prev}\n"));
+    }
+    report.push_str(&format!("expected:  {expected}\n"));
+    report.push_str(&format!("actual:    {actual}\n"));
+    report.push_str(&format!("           {}^", " ".repeat(column)));
+    report
+}
+
+#[test]
+fn nestest_golden_log() {
+    let log = std::fs::read_to_string(NESTEST_LOG).expect(NESTEST_LOG);
+    let expected: Vec<&str> = log.lines().map(str::trim_end).collect();
+
+    let mut cpu = nestest_cpu();
+    let mut history: Vec<String> = Vec::with_capacity(NESTEST_CONTEXT_LINES);
+    let mut line = 0;
+
+    cpu.run_with_callback(|cpu| {
+        let actual = trace(cpu);
+
+        if let Some(expected) = expected.get(line) {
+            assert!(
+                actual == *expected,
+                "{}",
+                nestest_divergence_report(line + 1, &history, expected, &actual)
+            );
+        } else {
+            assert!(
+                line < expected.len() + NESTEST_MAX_EXTRA_LINES,
+                "nestest ran {NESTEST_MAX_EXTRA_LINES} instructions past the end of {NESTEST_LOG}"
+            );
+        }
+
+        if history.len() == NESTEST_CONTEXT_LINES {
+            history.remove(0);
+        }
+        history.push(actual);
+        line += 1;
+    });
+
+    assert!(
+        line >= expected.len(),
+        "nestest stopped after {line} of {} reference lines",
+        expected.len()
+    );
+}
+
+#[test]
+fn nestest_result_codes() {
+    const OFFICIAL_RESULT_ADDR: u16 = 0x02;
+    const UNOFFICIAL_RESULT_ADDR: u16 = 0x03;
+
+    let mut cpu = nestest_cpu();
+    cpu.run();
+
+    assert_eq!(cpu.mem_read(OFFICIAL_RESULT_ADDR), 0x00);
+    assert_eq!(cpu.mem_read(UNOFFICIAL_RESULT_ADDR), 0x00);
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn tracer_applies_filters() {
+    let buffer = SharedBuffer::default();
+    let filter = TraceFilter {
+        pc_range: Some(0xC000..=0xC6FF),
+        bank: Some(0),
+        scanline_range: None,
+        start_addr: Some(0xC72D),
+    };
+    let mut tracer = Tracer::new(Box::new(buffer.clone()), filter);
+
+    let mut cpu = nestest_cpu();
+    cpu.run_with_callback(|cpu| tracer.trace(cpu).unwrap());
+    tracer.flush().unwrap();
+
+    let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+
+    // C5F5-C5FD are in range but run before the start address, and C72D itself is out of
+    // range, so the trace is the in range part of the log from C72D on
+    let log = std::fs::read_to_string(NESTEST_LOG).expect(NESTEST_LOG);
+    let start = log
+        .lines()
+        .position(|line| line.starts_with("C72D"))
+        .unwrap();
+    let expected: Vec<&str> = log
+        .lines()
+        .skip(start)
+        .map(str::trim_end)
+        .filter(|line| {
+            u16::from_str_radix(&line[..4], 16).is_ok_and(|pc| (0xC000..=0xC6FF).contains(&pc))
+        })
+        .collect();
+
+    assert!(!output.contains("C5FD "));
+    assert!(lines.len() >= expected.len());
+    assert_eq!(lines[..expected.len()], expected);
+}
+
+#[test]
+fn tracer_annotates_interrupts() {
+    #[rustfmt::skip]
+    let program = [
+        0xA9, 0x80,       // $8000 LDA #$80
+        0x8D, 0x00, 0x20, // $8002 STA $2000, NMI on
+        0x4C, 0x05, 0x80, // $8005 JMP $8005
+        0xE6, 0x10,       // $8008 NMI: INC $10
+        0xA5, 0x10,       // $800A LDA $10
+        0xC9, 0x02,       // $800C CMP #$02
+        0xF0, 0x01,       // $800E BEQ $8011 on the second NMI
+        0x40,             // $8010 RTI
+        0x00,             // $8011 BRK
+    ];
+    let bus = Bus::new(test_rom_with_nmi(&program, 0x8008), |_, _| {});
+    let mut cpu = CPU6502::new(bus);
+    cpu.reset();
+
+    let buffer = SharedBuffer::default();
+    let mut tracer = Tracer::new(Box::new(buffer.clone()), TraceFilter::default());
+    cpu.run_with_callback(|cpu| tracer.trace(cpu).unwrap());
+    tracer.flush().unwrap();
+
+    let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+
+    let nmi = lines
+        .iter()
+        .position(|line| *line == "---- NMI from $8005 ----")
+        .expect("missing NMI annotation");
+    assert!(
+        lines[nmi + 1].starts_with("8008  E6 10"),
+        "{}",
+        lines[nmi + 1]
+    );
+
+    let rti = lines
+        .iter()
+        .position(|line| *line == "---- RTI to $8005 ----")
+        .expect("missing RTI annotation");
+    assert!(rti > nmi);
+    assert!(lines[rti - 1].starts_with("8010  40"), "{}", lines[rti - 1]);
+
+    let nmis = lines
+        .iter()
+        .filter(|line| line.starts_with("---- NMI"))
+        .count();
+    assert_eq!(nmis, 2);
+    assert!(!output.contains("---- IRQ"));
+}
+
+#[test]
+fn tracer_filters_interrupt_annotations_with_the_handler() {
+    #[rustfmt::skip]
+    let program = [
+        0xA9, 0x80,       // $8000 LDA #$80
+        0x8D, 0x00, 0x20, // $8002 STA $2000, NMI on
+        0x4C, 0x05, 0x80, // $8005 JMP $8005
+        0xE6, 0x10,       // $8008 NMI: INC $10
+        0xA5, 0x10,       // $800A LDA $10
+        0xC9, 0x02,       // $800C CMP #$02
+        0xF0, 0x01,       // $800E BEQ $8011 on the second NMI
+        0x40,             // $8010 RTI
+        0x00,             // $8011 BRK
+    ];
+    let bus = Bus::new(test_rom_with_nmi(&program, 0x8008), |_, _| {});
+    let mut cpu = CPU6502::new(bus);
+    cpu.reset();
+
+    let buffer = SharedBuffer::default();
+    let filter = TraceFilter {
+        pc_range: Some(0x8000..=0x8007),
+        ..TraceFilter::default()
+    };
+    let mut tracer = Tracer::new(Box::new(buffer.clone()), filter);
+    cpu.run_with_callback(|cpu| tracer.trace(cpu).unwrap());
+    tracer.flush().unwrap();
+
+    let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    assert!(output.contains("8005  4C 05 80"));
+    assert!(!output.contains("----"), "{output}");
+}
+
+#[test]
+fn code_data_log_marks_prg_and_chr() {
+    use crate::emulator::cdl::*;
+
+    const PRG_SIZE: usize = 0x4000;
+    const CHR_SIZE: usize = 0x2000;
+    const C000_WINDOW_FLAGS: u8 = 0b10 << 2;
+
+    let mut cpu = nestest_cpu();
+    cpu.enable_code_data_log(CodeDataLog::new(PRG_SIZE, CHR_SIZE));
+    cpu.run();
+
+    let log = cpu.get_code_data_log().unwrap();
+    // JMP $C5F5 at $C000: opcode and operands are code, never data
+    for offset in 0..3 {
+        assert_eq!(log.prg[offset], PRG_CODE | C000_WINDOW_FLAGS);
+    }
+    assert!(log.prg.iter().any(|flags| flags & PRG_DATA != 0));
+    assert_eq!(log.to_bytes().len(), PRG_SIZE + CHR_SIZE);
+
+    let program = std::fs::read(NESTEST_ROM).unwrap();
+    let bus = Bus::new(Rom::new(&program).unwrap(), |_, _| {});
+    let mut cpu = CPU6502::new(bus);
+    cpu.enable_code_data_log(CodeDataLog::new(PRG_SIZE, CHR_SIZE));
+    cpu.reset();
+    while cpu.get_ppu().get_frame_count() < 10 {
+        cpu.step();
+    }
+
+    let log = cpu.get_code_data_log().unwrap();
+    assert!(log.chr.iter().any(|flags| flags & CHR_RENDERED != 0));
+}
+
 // fn trace(cpu: &CPU6502) -> String {
 //     let pc = cpu.program_counter;
 //     let opcode = cpu.bus.mem_read(pc);
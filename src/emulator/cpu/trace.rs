@@ -1,5 +1,8 @@
-use super::{AddressingMode, CPU6502};
+use super::{AddressingMode, CPU6502, Interrupt, STACK_ADDR};
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
 
 pub struct OpCode {
     pub code: u8,
@@ -362,12 +365,10 @@ lazy_static! {
     };
 }
 
-use super::MemAccess;
-
 pub fn trace(cpu: &mut CPU6502) -> String {
     let ref opscodes: HashMap<u8, &'static OpCode> = *OPCODES_MAP;
 
-    let code = cpu.mem_read(cpu.program_counter);
+    let code = cpu.bus.peek(cpu.program_counter);
     let ops = opscodes.get(&code).unwrap();
 
     let begin = cpu.program_counter;
@@ -380,7 +381,7 @@ pub fn trace(cpu: &mut CPU6502) -> String {
             cpu.program_counter += 1;
             let addr = cpu.get_operand_addr(&ops.mode);
             cpu.program_counter -= 1;
-            (addr, cpu.mem_read(addr))
+            (addr, cpu.bus.peek(addr))
         }
     };
 
@@ -390,7 +391,7 @@ pub fn trace(cpu: &mut CPU6502) -> String {
             _ => String::from(""),
         },
         2 => {
-            let address: u8 = cpu.mem_read(begin + 1);
+            let address: u8 = cpu.bus.peek(begin + 1);
             // let value = cpu.mem_read(address));
             hex_dump.push(address);
 
@@ -433,23 +434,23 @@ pub fn trace(cpu: &mut CPU6502) -> String {
             }
         }
         3 => {
-            let address_lo = cpu.mem_read(begin + 1);
-            let address_hi = cpu.mem_read(begin + 2);
+            let address_lo = cpu.bus.peek(begin + 1);
+            let address_hi = cpu.bus.peek(begin + 2);
             hex_dump.push(address_lo);
             hex_dump.push(address_hi);
 
-            let address = cpu.bus.mem_read_u16(begin + 1);
+            let address = cpu.bus.peek_u16(begin + 1);
 
             match ops.mode {
                 AddressingMode::Implicit => {
                     if ops.code == 0x6c {
                         //jmp indirect
                         let jmp_addr = if address & 0x00FF == 0x00FF {
-                            let lo = cpu.mem_read(address);
-                            let hi = cpu.mem_read(address & 0xFF00);
+                            let lo = cpu.bus.peek(address);
+                            let hi = cpu.bus.peek(address & 0xFF00);
                             (hi as u16) << 8 | (lo as u16)
                         } else {
-                            cpu.bus.mem_read_u16(address)
+                            cpu.bus.peek_u16(address)
                         };
 
                         // let jmp_addr = cpu.mem_read_u16(address);
@@ -485,14 +486,129 @@ pub fn trace(cpu: &mut CPU6502) -> String {
         .trim()
         .to_string();
 
+    let ppu = cpu.bus.get_ppu();
+
     format!(
-        "{:47} A:{:02x} X:{:02x} Y:{:02x} P:{:02x} SP:{:02x}",
+        "{:47} A:{:02x} X:{:02x} Y:{:02x} P:{:02x} SP:{:02x} PPU:{:>3},{:>3} CYC:{}",
         asm_str,
         cpu.accumulator,
         cpu.indx_reg_x,
         cpu.indx_reg_y,
         cpu.status_reg.status,
         cpu.stack_pointer,
+        ppu.get_scanline(),
+        ppu.get_dot(),
+        cpu.bus.get_cycles(),
     )
     .to_ascii_uppercase()
 }
+
+const RTI_OPCODE: u8 = 0x40;
+const STDOUT_PATH: &str = "-";
+
+#[derive(Default, Clone)]
+pub struct TraceFilter {
+    pub pc_range: Option<RangeInclusive<u16>>,
+    pub bank: Option<usize>,
+    pub scanline_range: Option<RangeInclusive<u16>>,
+    pub start_addr: Option<u16>,
+}
+
+pub struct Tracer {
+    out: BufWriter<Box<dyn Write>>,
+    filter: TraceFilter,
+    started: bool,
+    frame: usize,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write>, filter: TraceFilter) -> Self {
+        Tracer {
+            out: BufWriter::new(out),
+            started: filter.start_addr.is_none(),
+            filter,
+            frame: 0,
+        }
+    }
+
+    pub fn to_file(path: &str, filter: TraceFilter) -> io::Result<Self> {
+        let out: Box<dyn Write> = if path == STDOUT_PATH {
+            Box::new(io::stdout())
+        } else {
+            Box::new(File::create(path)?)
+        };
+
+        Ok(Self::new(out, filter))
+    }
+
+    pub fn trace(&mut self, cpu: &mut CPU6502) -> io::Result<()> {
+        let pc = cpu.program_counter;
+        let ppu = cpu.bus.get_ppu();
+        let scanline = ppu.get_scanline();
+
+        // the process may exit from inside the emulation loop, so flush once per frame
+        if self.frame != ppu.get_frame_count() {
+            self.frame = ppu.get_frame_count();
+            self.out.flush()?;
+        }
+
+        if !self.started {
+            if self.filter.start_addr != Some(pc) {
+                return Ok(());
+            }
+            self.started = true;
+        }
+
+        if !in_range(&self.filter.scanline_range, scanline) {
+            return Ok(());
+        }
+
+        let bank_matches = self
+            .filter
+            .bank
+            .is_none_or(|bank| cpu.bus.prg_bank(pc) == Some(bank));
+        if !in_range(&self.filter.pc_range, pc) || !bank_matches {
+            return Ok(());
+        }
+
+        // pc is the first instruction of the handler, so the annotation follows its filter
+        if let Some(interrupt) = cpu.last_interrupt {
+            let name = match interrupt {
+                Interrupt::Nmi => "NMI",
+                Interrupt::Irq => "IRQ",
+            };
+            writeln!(
+                self.out,
+                "---- {name} from ${:04X} ----",
+                stack_return_addr(cpu)
+            )?;
+        }
+
+        writeln!(self.out, "{}", trace(cpu))?;
+
+        if cpu.bus.peek(pc) == RTI_OPCODE {
+            writeln!(self.out, "---- RTI to ${:04X} ----", stack_return_addr(cpu))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+fn in_range<T: PartialOrd>(range: &Option<RangeInclusive<T>>, val: T) -> bool {
+    range.as_ref().is_none_or(|range| range.contains(&val))
+}
+
+// the return address of an interrupt frame sits above the pushed status byte
+fn stack_return_addr(cpu: &CPU6502) -> u16 {
+    let lo = cpu
+        .bus
+        .peek(STACK_ADDR + cpu.stack_pointer.wrapping_add(2) as u16);
+    let hi = cpu
+        .bus
+        .peek(STACK_ADDR + cpu.stack_pointer.wrapping_add(3) as u16);
+    u16::from_le_bytes([lo, hi])
+}
@@ -1,31 +1,47 @@
 mod address_reg;
+mod background_shifter;
 mod control_reg;
+pub mod event_log;
 mod internal_regs;
+mod io_latch;
+pub mod layers;
 mod mask_reg;
+pub mod nametables;
 pub mod render;
 mod scroll_reg;
+mod sprite_eval;
 mod status_reg;
 
-use core::panic;
-
-use super::rom::Mirroring;
+use super::cdl::{CHR_READ, CHR_RENDERED};
 use address_reg::AddressReg;
+use background_shifter::BackgroundShifter;
 use control_reg::*;
+use event_log::{EventLog, PpuEvent};
 use internal_regs::*;
+use io_latch::{FULL_BYTE, IoLatch};
+use layers::Layers;
 use mask_reg::MaskReg;
-use render::{frame::Frame, pallete_table::SYSTEM_PALLETE};
+use nametables::Nametables;
+use render::frame::{Frame, IndexFrame};
+use render::palette::Palette;
+use render::pallete_table::GREYSCALE_MASK;
 use scroll_reg::ScrollReg;
-use status_reg::{SPRITE_0_HIT_FLAG, StatusReg};
+use sprite_eval::{SpriteEval, SpriteUnit};
+use status_reg::{PPU_OPEN_BUS, SPRITE_0_HIT_FLAG, StatusReg};
 
 pub struct Ppu {
     chr_rom: Vec<u8>,
     pub screen: Frame,
+    pub screen_indices: IndexFrame,
     palette_table: [u8; PALETTE_TABLE_SIZE],
-    vram: [u8; VRAM_SIZE],
+    palette: Palette,
+    nametables: Nametables,
     oam_data: [u8; OAM_DATA_SIZE],
-    oam_cache: [u8; OAM_CACHE_SIZE],
-    oam_cache_len: u8,
-    mirroring: Mirroring,
+    sprite_eval: SpriteEval,
+    sprite_units: Vec<SpriteUnit>,
+    sprite_limit: bool,
+    layers: Layers,
+    sprite_zero_in_units: bool,
     ctrl_reg: ControlReg,
     mask_reg: MaskReg,
     status_reg: StatusReg,
@@ -33,30 +49,28 @@ pub struct Ppu {
     scroll_reg: ScrollReg,
     addr_reg: AddressReg,
     internal_regs: InternalRegs,
+    bg_shifter: BackgroundShifter,
     internal_data_buf: u8,
+    io_latch: IoLatch,
     scanline: u16,
     cycles: usize,
     nmi_interrupt: Option<u8>,
     is_odd_frame: bool,
+    frame_count: usize,
+    chr_log: Option<Vec<u8>>,
+    event_log: Option<EventLog>,
 }
 
 const PALETTE_TABLE_SIZE: usize = 32;
-const VRAM_SIZE: usize = 2048;
 const OAM_DATA_SIZE: usize = 256;
 const OAM_CACHE_SIZE: usize = 8;
+const OAM_ROW_SIZE: u8 = 8;
 
 const ROM_ADDR: u16 = 0x0000;
 const VRAM_ADDR: u16 = 0x2000;
-const VRAM_END_ADDR: u16 = 0x3000;
 const PALETTES_ADDR: u16 = 0x3F00;
-const MIRRORS_ADDR: u16 = 0x4000;
-
-const MIRROR_DOWN_VRAM_ADDR_MASK: u16 = 0b1011_1111_1111_1111;
-const NAME_TABLE_SIZE: u16 = 0x0400;
-const NAME_TABLE_0: u16 = 0;
-const NAME_TABLE_1: u16 = 1;
-const NAME_TABLE_2: u16 = 2;
-const NAME_TABLE_3: u16 = 3;
+const PALETTE_SHADOW_OFFSET: u16 = 0x1000;
+const PALETTE_DATA_MASK: u8 = 0b0011_1111;
 
 const VISIBLE_SCANLINES: u16 = 239;
 const SCANLINES_PER_FRAME: u16 = 262;
@@ -66,25 +80,39 @@ const DOT_256_IN_SCANLINE: usize = 256;
 const DOT_257_IN_SCANLINE: usize = 257;
 const DOT_280_IN_SCANLINE: usize = 280;
 const DOT_304_IN_SCANLINE: usize = 304;
-const DOT_328_IN_SCANLINE: usize = 328;
+const DOT_321_IN_SCANLINE: usize = 321;
+const DOT_322_IN_SCANLINE: usize = 322;
+const DOT_336_IN_SCANLINE: usize = 336;
+const DOT_337_IN_SCANLINE: usize = 337;
+const DOT_338_IN_SCANLINE: usize = 338;
+const DOT_320_IN_SCANLINE: usize = 320;
 const DOT_340_IN_SCANLINE: usize = 340;
+const DOT_64_IN_SCANLINE: usize = 64;
+const DOT_65_IN_SCANLINE: usize = 65;
 const PRE_RENDER_SCANLINE: u16 = 261;
 const VISIBLE_DOTS: u16 = 256;
+const TILE_FETCH_DOTS: usize = 8;
 
+const SPRITE_ATTR_MASK: u8 = 0b1110_0011;
+const SPRITE_ATTR_BYTE: u8 = 2;
 const FLIP_HORIZONTAL: u8 = 0b0100_0000;
 const FLIP_VERTICAL: u8 = 0b1000_0000;
 
 impl Ppu {
-    pub fn new(chr_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+    pub fn new(chr_rom: Vec<u8>, nametables: Nametables) -> Self {
         Ppu {
             chr_rom,
-            mirroring,
+            nametables,
             screen: Frame::new(),
-            vram: [0; VRAM_SIZE],
+            screen_indices: IndexFrame::new(),
             oam_data: [0; OAM_DATA_SIZE],
-            oam_cache: [0; OAM_CACHE_SIZE],
-            oam_cache_len: 0,
+            sprite_eval: SpriteEval::new(),
+            sprite_units: vec![SpriteUnit::new(); OAM_CACHE_SIZE],
+            sprite_limit: true,
+            layers: Layers::default(),
+            sprite_zero_in_units: false,
             palette_table: [0; PALETTE_TABLE_SIZE],
+            palette: Palette::default(),
             ctrl_reg: ControlReg::new(),
             mask_reg: MaskReg::new(),
             status_reg: StatusReg::new(),
@@ -92,15 +120,21 @@ impl Ppu {
             scroll_reg: ScrollReg::new(),
             addr_reg: AddressReg::new(),
             internal_regs: InternalRegs::new(),
+            bg_shifter: BackgroundShifter::new(),
             internal_data_buf: 0,
+            io_latch: IoLatch::new(),
             scanline: 0,
             cycles: 0,
             nmi_interrupt: None,
             is_odd_frame: false,
+            frame_count: 0,
+            chr_log: None,
+            event_log: None,
         }
     }
 
     pub fn write_to_ctrl(&mut self, value: u8) {
+        self.drive_io_latch(value, FULL_BYTE);
         let prev_nmi_status = self.ctrl_reg.gen_vblank_nmi();
         self.ctrl_reg.update(value);
         self.internal_regs.ctrl_write(value);
@@ -110,47 +144,112 @@ impl Ppu {
     }
 
     pub fn write_to_mask(&mut self, value: u8) {
+        self.drive_io_latch(value, FULL_BYTE);
         self.mask_reg.update(value);
     }
 
+    // PPUSTATUS only drives its 3 flag bits, the rest is whatever is left on the bus
     pub fn read_status(&mut self) -> u8 {
-        let data = self.status_reg.get();
+        let flags = self.status_reg.get() & !PPU_OPEN_BUS;
         self.status_reg.reset_vblank();
         self.internal_regs.status_read();
-        data
+        self.drive_io_latch(flags, !PPU_OPEN_BUS);
+        self.read_open_bus()
+    }
+
+    // the status register is read only, a write just charges the bus
+    pub fn write_to_status(&mut self, value: u8) {
+        self.drive_io_latch(value, FULL_BYTE);
+    }
+
+    // reads of the write only registers return the decaying I/O latch
+    pub fn read_open_bus(&self) -> u8 {
+        self.io_latch.get(self.frame_count)
+    }
+
+    fn drive_io_latch(&mut self, value: u8, mask: u8) {
+        self.io_latch.refresh(value, mask, self.frame_count);
     }
 
     pub fn write_to_oam_addr(&mut self, value: u8) {
+        self.drive_io_latch(value, FULL_BYTE);
         self.oam_addr_reg = value;
     }
 
-    pub fn read_oam_data(&self) -> u8 {
-        self.oam_data[self.oam_addr_reg as usize]
+    pub fn read_oam_data(&mut self) -> u8 {
+        let data = if self.is_rendering_line() {
+            self.sprite_eval.get_latch()
+        } else {
+            self.oam_data[self.oam_addr_reg as usize]
+        };
+        self.drive_io_latch(data, FULL_BYTE);
+        data
     }
 
     pub fn write_to_oam_data(&mut self, value: u8) {
-        self.oam_data[self.oam_addr_reg as usize] = value;
+        self.drive_io_latch(value, FULL_BYTE);
+        // writes during rendering are dropped but still bump the sprite index of OAMADDR
+        if self.is_rendering_line() {
+            self.oam_addr_reg = self
+                .oam_addr_reg
+                .wrapping_add(sprite_eval::SPRITE_BYTES as u8);
+            return;
+        }
+        self.store_oam_byte(self.oam_addr_reg, value);
         self.oam_addr_reg = self.oam_addr_reg.wrapping_add(1);
     }
 
+    // the attribute byte has no storage for bits 2-4
+    fn store_oam_byte(&mut self, addr: u8, value: u8) {
+        let value = if (addr & 0b11) == SPRITE_ATTR_BYTE {
+            value & SPRITE_ATTR_MASK
+        } else {
+            value
+        };
+        self.oam_data[addr as usize] = value;
+    }
+
     pub fn write_to_scroll(&mut self, value: u8) {
+        self.drive_io_latch(value, FULL_BYTE);
         // self.scroll_reg.write(value, self.internal_regs.get_w());
         self.internal_regs.scroll_write(value);
     }
 
     pub fn write_to_ppu_addr(&mut self, value: u8) {
+        self.drive_io_latch(value, FULL_BYTE);
         // self.addr_reg.update(value, self.internal_regs.get_w());
         self.internal_regs.addr_write(value);
     }
 
     pub fn read_data(&mut self) -> u8 {
-        let res = self.read_vram(self.internal_regs.get_v());
+        let addr = self.internal_regs.get_v() & 0x3FFF;
+        let res = if addr >= PALETTES_ADDR {
+            self.read_palette_data(addr)
+        } else {
+            let data = self.read_vram(addr);
+            self.drive_io_latch(data, FULL_BYTE);
+            data
+        };
         self.handle_data_internal_regs();
 
         res
     }
 
+    // palette reads skip the buffer, which is refilled from the nametable underneath.
+    // only the 6 palette bits are driven, the top 2 come from the bus
+    fn read_palette_data(&mut self, addr: u16) -> u8 {
+        self.internal_data_buf = self.internal_read_vram(addr - PALETTE_SHADOW_OFFSET);
+
+        let mut color = self.palette_table[Self::get_palette_table_index(addr)] & PALETTE_DATA_MASK;
+        if self.mask_reg.is_greyscale() {
+            color &= GREYSCALE_MASK;
+        }
+        self.drive_io_latch(color, PALETTE_DATA_MASK);
+        self.read_open_bus()
+    }
+
     pub fn write_to_data(&mut self, value: u8) {
+        self.drive_io_latch(value, FULL_BYTE);
         self.write_to_vram(value);
         self.handle_data_internal_regs();
     }
@@ -163,21 +262,18 @@ impl Ppu {
             ROM_ADDR..VRAM_ADDR => {
                 println!("attempt to write to rom space: {addr}")
             }
-            VRAM_ADDR..VRAM_END_ADDR => {
-                self.vram[self.mirror_vram_addr(addr) as usize] = value;
+            VRAM_ADDR..PALETTES_ADDR => {
+                self.nametables.write(addr, value);
             }
-            VRAM_END_ADDR..PALETTES_ADDR => {
-                panic!("addr space 0x3000..0x3f00 not expected to be used")
-            }
-            PALETTES_ADDR..MIRRORS_ADDR => {
+            _ => {
                 self.palette_table[Self::get_palette_table_index(addr)] = value;
             }
-            _ => panic!("unexpected access to mirrored space {addr}"),
         }
         // self.increment_vram_addr();
     }
 
     fn read_vram(&mut self, addr: u16) -> u8 {
+        self.log_chr(addr, CHR_READ);
         let res = self.internal_data_buf;
         self.internal_data_buf = self.internal_read_vram(addr);
         res
@@ -188,12 +284,8 @@ impl Ppu {
 
         match mask_addr {
             ROM_ADDR..VRAM_ADDR => self.chr_rom[mask_addr as usize],
-            VRAM_ADDR..VRAM_END_ADDR => self.vram[self.mirror_vram_addr(mask_addr) as usize],
-            VRAM_END_ADDR..PALETTES_ADDR => {
-                panic!("addr space 0x3000..0x3f00 not expected to be used")
-            }
-            PALETTES_ADDR..MIRRORS_ADDR => self.palette_table[Self::get_palette_table_index(addr)],
-            _ => panic!("unexpected access to mirrored space {addr}"),
+            VRAM_ADDR..PALETTES_ADDR => self.nametables.read(mask_addr),
+            _ => self.palette_table[Self::get_palette_table_index(addr)],
         }
     }
 
@@ -213,12 +305,19 @@ impl Ppu {
     }
 
     pub fn write_to_oam_dma(&mut self, data: &[u8; OAM_DATA_SIZE]) {
-        self.oam_data.copy_from_slice(data);
+        for (i, byte) in data.iter().enumerate() {
+            self.store_oam_byte(self.oam_addr_reg.wrapping_add(i as u8), *byte);
+        }
+        // DMA goes through OAMDATA, the last byte stays on the bus
+        self.drive_io_latch(data[OAM_DATA_SIZE - 1], FULL_BYTE);
     }
 
     pub fn tick(&mut self) {
         match self.scanline {
             0..=VISIBLE_SCANLINES => {
+                if self.is_rendering() {
+                    self.fetch_background();
+                }
                 if (self.cycles > 0) && (self.cycles <= VISIBLE_DOTS as usize) {
                     self.render_viseble_dots();
                 }
@@ -230,13 +329,18 @@ impl Ppu {
                 //     self.status_reg.set_sprite_zero_hit();
                 // }
 
-                if (self.cycles == DOT_340_IN_SCANLINE) && self.is_rendering() {
-                    self.evaluate_sprites_for_scanline(self.scanline + 1);
+                if self.is_rendering() {
+                    self.evaluate_sprites();
+                    self.fetch_sprites();
                 }
             }
             240 => {}
             VERTICAL_BLANKING_LINES => {
                 if self.cycles == 1 {
+                    self.frame_count += 1;
+                    if let Some(log) = self.event_log.as_mut() {
+                        log.end_frame();
+                    }
                     self.status_reg.set_vblank();
                     self.status_reg.unset_sprite_zero_hit();
 
@@ -248,6 +352,7 @@ impl Ppu {
             242..PRE_RENDER_SCANLINE => {}
             PRE_RENDER_SCANLINE => {
                 if self.is_rendering() {
+                    self.fetch_background();
                     self.non_vblank_scanlines();
                     if (self.cycles >= DOT_280_IN_SCANLINE) && (self.cycles <= DOT_304_IN_SCANLINE)
                     {
@@ -255,12 +360,18 @@ impl Ppu {
                     }
                 }
                 if self.cycles == 1 {
+                    if self.is_rendering() {
+                        self.corrupt_oam();
+                    }
                     self.nmi_interrupt = None;
                     self.status_reg.unset_sprite_zero_hit();
+                    self.status_reg.unset_sprite_overflow();
                     self.status_reg.reset_vblank();
+                    // no evaluation on the pre-render line, so nothing is drawn on line 0
+                    self.sprite_eval.reset();
                 }
-                if (self.cycles == DOT_340_IN_SCANLINE) && self.is_rendering() {
-                    self.evaluate_sprites_for_scanline(0);
+                if self.is_rendering() {
+                    self.fetch_sprites();
                 }
             }
             SCANLINES_PER_FRAME => {
@@ -283,33 +394,138 @@ impl Ppu {
         // println!("scanline: {}, dot: {}", self.scanline, self.cycles);
     }
 
-    fn evaluate_sprites_for_scanline(&mut self, target_scanline: u16) {
-        self.oam_cache.fill(0);
-        self.oam_cache_len = 0;
-        let range = self.ctrl_reg.sprite_size();
+    fn evaluate_sprites(&mut self) {
+        match self.cycles {
+            1..=DOT_64_IN_SCANLINE => self.sprite_eval.clear(self.cycles),
+            DOT_65_IN_SCANLINE..=DOT_256_IN_SCANLINE => {
+                let overflow = self.sprite_eval.step(
+                    self.cycles,
+                    &self.oam_data,
+                    &mut self.oam_addr_reg,
+                    self.scanline,
+                    self.ctrl_reg.sprite_size(),
+                );
+                if overflow {
+                    self.status_reg.set_sprite_overflow();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // dots 257-320: 8 dots per sprite slot, garbage nametable fetches then the pattern bytes
+    fn fetch_sprites(&mut self) {
+        if (self.cycles < DOT_257_IN_SCANLINE) || (self.cycles > DOT_320_IN_SCANLINE) {
+            return;
+        }
+
+        self.oam_addr_reg = 0;
+        let slot = (self.cycles - DOT_257_IN_SCANLINE) / TILE_FETCH_DOTS;
+        let step = (self.cycles - DOT_257_IN_SCANLINE) & (TILE_FETCH_DOTS - 1);
+        self.sprite_eval.fetch_read(slot, step);
+
+        if slot == 0 && step == 0 {
+            self.sprite_zero_in_units = self.sprite_eval.is_sprite_zero_found();
+            self.sprite_units.truncate(OAM_CACHE_SIZE);
+        }
+
+        match step {
+            0 | 2 => {
+                self.internal_read_vram(self.internal_regs.fetch_tile_addr());
+            }
+            4 | 6 => {
+                let is_active = slot < self.sprite_eval.get_found();
+                let sprite = self.sprite_eval.get_sprite(slot);
+                let (attr, sprite_x) = (sprite[2], sprite[3]);
+                let plane_offset = if step == 4 { 0 } else { 8 };
 
-        for i in 0..64 {
-            let sprite_y = self.oam_data[i as usize * 4] as i16 + 1;
-            let diff = target_scanline as i16 - sprite_y;
+                let (sprite_y, tile) = (sprite[0], sprite[1]);
 
-            if (diff >= 0) && (diff < range as i16) {
-                self.oam_cache[self.oam_cache_len as usize] = i * 4;
-                self.oam_cache_len += 1;
-                if self.oam_cache_len >= 8 {
-                    break;
+                // empty slots still fetch so the pattern bus sees the same addresses
+                let pattern = if is_active {
+                    self.fetch_sprite_pattern(sprite_y, tile, attr, plane_offset)
+                } else {
+                    let pattern_addr = self.sprite_pattern_addr(sprite_y, tile, attr);
+                    self.internal_read_vram(pattern_addr + plane_offset);
+                    0
+                };
+
+                let oam_index = self.sprite_eval.get_oam_index(slot);
+                let unit = &mut self.sprite_units[slot];
+                unit.attr = attr;
+                unit.x = sprite_x;
+                unit.oam_index = oam_index;
+                if step == 4 {
+                    unit.pattern_lo = pattern;
+                } else {
+                    unit.pattern_hi = pattern;
                 }
             }
+            _ => {}
+        }
+
+        if self.cycles == DOT_320_IN_SCANLINE && !self.sprite_limit {
+            self.fetch_extra_sprites();
         }
     }
 
-    fn non_vblank_scanlines(&mut self) {
-        // if (self.cycles != 0)
-        //     && (self.cycles >= DOT_328_IN_SCANLINE)
-        //     && ((self.cycles & (8 - 1)) == 0)
-        // {
-        //     self.internal_regs.coarse_x_inc();
-        // }
+    // sprites past the 8th are taken straight from primary oam, the game still sees
+    // the overflow flag from the hardware evaluation
+    fn fetch_extra_sprites(&mut self) {
+        if self.sprite_eval.get_found() < OAM_CACHE_SIZE {
+            return;
+        }
+
+        let height = self.ctrl_reg.sprite_size() as u16;
+        let scanline = self.scanline;
+        // a stack copy of OAM, so the pattern fetches can borrow self while it is walked
+        let oam = self.oam_data;
+        let extra_sprites = oam
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, sprite)| scanline.wrapping_sub(sprite[0] as u16) < height)
+            .skip(OAM_CACHE_SIZE)
+            .map(|(index, sprite)| (index, [sprite[0], sprite[1], sprite[2], sprite[3]]));
+
+        for (index, [sprite_y, tile, attr, sprite_x]) in extra_sprites {
+            let mut unit = SpriteUnit::new();
+            unit.oam_index = index as u8;
+            unit.pattern_lo = self.fetch_sprite_pattern(sprite_y, tile, attr, 0);
+            unit.pattern_hi = self.fetch_sprite_pattern(sprite_y, tile, attr, 8);
+            unit.attr = attr;
+            unit.x = sprite_x;
+            self.sprite_units.push(unit);
+        }
+    }
+
+    fn fetch_sprite_pattern(&mut self, sprite_y: u8, tile: u8, attr: u8, plane_offset: u16) -> u8 {
+        let pattern_addr = self.sprite_pattern_addr(sprite_y, tile, attr) + plane_offset;
+        self.log_chr(pattern_addr, CHR_RENDERED);
+
+        let pattern = self.internal_read_vram(pattern_addr);
+        if (attr & FLIP_HORIZONTAL) != 0 {
+            pattern.reverse_bits()
+        } else {
+            pattern
+        }
+    }
+
+    fn sprite_pattern_addr(&self, sprite_y: u8, tile: u8, attr: u8) -> u16 {
+        let height = self.ctrl_reg.sprite_size() as u16;
+        let mut row = self.scanline.wrapping_sub(sprite_y as u16) & (height - 1);
+        if (attr & FLIP_VERTICAL) != 0 {
+            row ^= height - 1;
+        }
+
+        let tile = tile as u16;
+        if height == 16 {
+            ((tile & 1) << 12) | (((tile & !1) * 16) + (row & 7) + ((row & 8) << 1))
+        } else {
+            (tile * 16 + row) | self.ctrl_reg.sprt_pattern_addr()
+        }
+    }
 
+    fn non_vblank_scanlines(&mut self) {
         if self.cycles == DOT_256_IN_SCANLINE {
             self.internal_regs.coarse_y_inc();
         }
@@ -318,10 +534,26 @@ impl Ppu {
         }
     }
 
+    // rendering that starts with OAMADDR at 8 or above copies the 8 bytes
+    // of its row over the first 8 bytes of OAM
+    fn corrupt_oam(&mut self) {
+        if self.oam_addr_reg < OAM_ROW_SIZE {
+            return;
+        }
+        let row = (self.oam_addr_reg & !(OAM_ROW_SIZE - 1)) as usize;
+        self.oam_data
+            .copy_within(row..row + OAM_ROW_SIZE as usize, 0);
+    }
+
     fn is_rendering(&self) -> bool {
         self.mask_reg.show_sprites() | self.mask_reg.show_background()
     }
 
+    fn is_rendering_line(&self) -> bool {
+        self.is_rendering()
+            && ((self.scanline <= VISIBLE_SCANLINES) || (self.scanline == PRE_RENDER_SCANLINE))
+    }
+
     fn is_sprite_0_hit(&self, cycle: usize) -> bool {
         let y = self.oam_data[0] as usize;
         let x = self.oam_data[3] as usize;
@@ -333,22 +565,6 @@ impl Ppu {
         self.addr_reg.increment(self.ctrl_reg.vram_addr_increment());
     }
 
-    fn mirror_vram_addr(&self, addr: u16) -> u16 {
-        let mirrored_vram = addr & MIRROR_DOWN_VRAM_ADDR_MASK;
-        let vram_indx = mirrored_vram - VRAM_ADDR;
-        let name_table = vram_indx / NAME_TABLE_SIZE;
-
-        use Mirroring::*;
-        match (&self.mirroring, name_table) {
-            (Vertical, NAME_TABLE_2) | (Vertical, NAME_TABLE_3) => {
-                vram_indx - (2 * NAME_TABLE_SIZE)
-            }
-            (Horizontal, NAME_TABLE_1) | (Horizontal, NAME_TABLE_2) => vram_indx - NAME_TABLE_SIZE,
-            (Horizontal, NAME_TABLE_3) => vram_indx - (2 * NAME_TABLE_SIZE),
-            _ => vram_indx,
-        }
-    }
-
     pub fn take_nmi_interrupt(&mut self) -> Option<u8> {
         self.nmi_interrupt.take()
     }
@@ -357,177 +573,256 @@ impl Ppu {
         self.nmi_interrupt.is_some()
     }
 
+    pub fn get_scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn get_dot(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn get_nametables(&self) -> &Nametables {
+        &self.nametables
+    }
+
+    pub fn get_chr(&self) -> &[u8] {
+        &self.chr_rom
+    }
+
+    pub fn get_oam(&self) -> &[u8] {
+        &self.oam_data
+    }
+
+    pub fn get_palette_table(&self) -> &[u8] {
+        &self.palette_table
+    }
+
+    pub fn get_palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn get_bg_pattern_addr(&self) -> u16 {
+        self.ctrl_reg.bknd_pattern_addr()
+    }
+
+    pub fn get_sprite_pattern_addr(&self) -> u16 {
+        self.ctrl_reg.sprt_pattern_addr()
+    }
+
+    pub fn get_sprite_height(&self) -> u8 {
+        self.ctrl_reg.sprite_size()
+    }
+
+    // top left corner of the next frame in the 512x480 space of the four nametables
+    pub fn get_scroll(&self) -> (usize, usize) {
+        let nametable = self.internal_regs.get_nametable_select() as usize;
+        (
+            (nametable & 1) * Frame::WIDTH + self.internal_regs.get_scroll_x() as usize,
+            (nametable >> 1) * Frame::HIGHT + self.internal_regs.get_scroll_y() as usize,
+        )
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn set_sprite_limit(&mut self, enabled: bool) {
+        self.sprite_limit = enabled;
+    }
+
+    pub fn get_layers(&self) -> Layers {
+        self.layers
+    }
+
+    pub fn set_layers(&mut self, layers: Layers) {
+        self.layers = layers;
+    }
+
+    pub fn enable_chr_log(&mut self, log: Vec<u8>) {
+        self.chr_log = Some(log);
+    }
+
+    pub fn get_chr_log(&self) -> Option<&[u8]> {
+        self.chr_log.as_deref()
+    }
+
+    pub fn enable_event_log(&mut self) {
+        self.event_log = Some(EventLog::new());
+    }
+
+    pub fn get_event_log(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    pub fn log_write(&mut self, addr: u16, value: u8) {
+        if let Some(log) = self.event_log.as_mut() {
+            log.record(PpuEvent {
+                scanline: self.scanline,
+                dot: self.cycles,
+                addr,
+                value,
+            });
+        }
+    }
+
+    fn log_chr(&mut self, addr: u16, flags: u8) {
+        if let Some(flag) = self
+            .chr_log
+            .as_mut()
+            .and_then(|log| log.get_mut(addr as usize))
+        {
+            *flag |= flags;
+        }
+    }
+
     fn render_viseble_dots(&mut self) {
         let x = (self.cycles - 1) as u16;
-        let fine_x = (self.internal_regs.get_x() as u16 + x) & (8 - 1);
         let mut palette_addr: u8 = 0;
         let mut palette_addr_sp: u8 = 0;
         let mut back_priority: u8 = 0;
 
         if self.mask_reg.show_background() {
             palette_addr = self.render_background() as u8;
-            if fine_x == 7 {
-                self.internal_regs.coarse_x_inc();
-            }
         }
         if self.mask_reg.show_sprites() && (self.mask_reg.show_sprite_8() || (x >= 8)) {
             palette_addr_sp = self.render_sprites(palette_addr as u16, &mut back_priority) as u8;
         }
+        // hidden layers still take part in sprite 0 hit
+        if self.layers.hide_background {
+            palette_addr = 0;
+        }
         if ((palette_addr == 0) && (palette_addr_sp != 0))
             || ((palette_addr != 0) && (palette_addr_sp != 0) && (back_priority == 0))
         {
             palette_addr = palette_addr_sp;
         }
 
-        let palette = self.palette_table[palette_addr as usize];
-        self.screen.set_pixel(
-            x as usize,
-            self.scanline as usize,
-            SYSTEM_PALLETE[palette as usize],
-        );
+        let mut palette = self.palette_table[palette_addr as usize];
+        if self.mask_reg.is_greyscale() {
+            palette &= GREYSCALE_MASK;
+        }
+
+        let emphasis = self.mask_reg.emphasis();
+        let rgb = self.palette.color(palette, emphasis);
+        self.screen
+            .set_pixel(x as usize, self.scanline as usize, rgb);
+        self.screen_indices
+            .set_pixel(x as usize, self.scanline as usize, palette, emphasis);
     }
 
-    fn render_background(&mut self) -> u16 {
+    fn render_background(&self) -> u16 {
         let x = (self.cycles - 1) as u16;
-        let fine_x = (self.internal_regs.get_x() as u16 + x) & (8 - 1);
-        let v = self.internal_regs.get_v();
-        let mut res = 0;
-
-        if (self.mask_reg.show_background_8()) || (x >= 8) {
-            let tile_addr = self.internal_regs.fetch_tile_addr();
-            let attr_addr = self.internal_regs.fetch_attr_addr();
-
-            let pattern_addr = (self.internal_read_vram(tile_addr) as u16 * 16 + ((v >> 12) & 0x7))
-                | self.ctrl_reg.bknd_pattern_addr();
 
-            let mut palette_addr = (self.internal_read_vram(pattern_addr) >> (7 ^ fine_x)) & 1;
-            palette_addr |= ((self.internal_read_vram(pattern_addr + 8) >> (7 ^ fine_x)) & 1) << 1;
-
-            if palette_addr != 0 {
-                let attr = self.internal_read_vram(attr_addr);
-                res = palette_addr as u16 | (((attr as u16 >> ((v >> 4) & 4 | v & 2)) & 0x3) << 2);
-            }
+        if !self.mask_reg.show_background_8() && (x < 8) {
+            return 0;
         }
-        res
-    }
-
-    // fn render_background(&mut self) -> u16 {
-    //     let x = (self.cycles - 1) as u16; // Current pixel (0-255)
-    //     let v = self.internal_regs.get_v(); // Base VRAM address for the scanline
-    //     let fine_x_scroll = self.internal_regs.get_x() as u16; // 3-bit fine X scroll
-    //     let mut res = 0;
-
-    //     if (self.mask_reg.show_background_8()) || (x >= 8) {
-    //         // --- NEW PER-PIXEL LOGIC ---
-
-    //         // 1. Calculate the total horizontal pixel position on the 512-pixel-wide
-    //         //    virtual nametable.
-    //         let total_pixel_x = x + ((v & COARSE_X_SCROLL) * 8) + fine_x_scroll;
-
-    //         // 2. Get the fine_x (0-7) for *this specific pixel* from the total.
-    //         let fine_x = total_pixel_x & 7;
-
-    //         // 3. Get the coarse_x (0-31) for *this specific pixel*.
-    //         let coarse_x = (total_pixel_x >> 3) & 31;
-
-    //         // 4. Get the base horizontal nametable (bit 10 of v).
-    //         let base_nametable = v & 0x0400;
 
-    //         // 5. Check if we've crossed a nametable boundary (e.g., pixel 256).
-    //         let nametable_wrap = (total_pixel_x >> 8) & 1; // 1 if total_pixel_x >= 256
-
-    //         // 6. XOR the base nametable with the wrap bit to get the *correct* nametable.
-    //         let pixel_nametable = base_nametable ^ (nametable_wrap << 10);
-
-    //         // 7. Construct a temporary 'v' for *this pixel only*.
-    //         //    It uses the Y info from the original 'v' but the new
-    //         //    horizontal info we just calculated.
-    //         let pixel_v = (v & !(COARSE_X_SCROLL | 0x0400)) // Clear old horizontal bits
-    //                       | coarse_x                        // Add new coarse_x
-    //                       | pixel_nametable; // Add new nametable select
-
-    //         // --- END NEW LOGIC ---
+        self.bg_shifter.pixel(self.internal_regs.get_x()) as u16
+    }
 
-    //         // Now, the rest of the function uses our new 'pixel_v' and 'fine_x'
+    fn fetch_background(&mut self) {
+        let cycle = self.cycles;
+        let is_fetch_dot = (1..=VISIBLE_DOTS as usize).contains(&cycle)
+            || (DOT_321_IN_SCANLINE..=DOT_336_IN_SCANLINE).contains(&cycle);
 
-    //         let tile_addr = VRAM_ADDR | (pixel_v & 0x0FFF);
-    //         let attr_addr =
-    //             0x23C0 | (pixel_v & 0x0C00) | ((pixel_v >> 4) & 0x38) | ((pixel_v >> 2) & 0x07);
+        if (2..=DOT_257_IN_SCANLINE).contains(&cycle)
+            || (DOT_322_IN_SCANLINE..=DOT_337_IN_SCANLINE).contains(&cycle)
+        {
+            self.bg_shifter.shift();
+        }
 
-    //         // Fine Y scroll is constant for the whole line, so reading 'v' here is fine.
-    //         let pattern_addr = (self.internal_read_vram(tile_addr) as u16 * 16 + ((v >> 12) & 0x7))
-    //             | self.ctrl_reg.bknd_pattern_addr();
+        if is_fetch_dot {
+            match (cycle - 1) & (TILE_FETCH_DOTS - 1) {
+                0 => {
+                    self.bg_shifter.load();
+                    let tile_id = self.internal_read_vram(self.internal_regs.fetch_tile_addr());
+                    self.bg_shifter.set_next_tile_id(tile_id);
+                }
+                2 => {
+                    let v = self.internal_regs.get_v();
+                    let attr = self.internal_read_vram(self.internal_regs.fetch_attr_addr());
+                    self.bg_shifter.set_next_attr(
+                        attr,
+                        v & COARSE_X_SCROLL,
+                        (v & COARSE_Y_SCROLL) >> 5,
+                    );
+                }
+                4 => {
+                    let pattern_addr = self.bg_pattern_addr();
+                    let pattern = self.internal_read_vram(pattern_addr);
+                    self.log_chr(pattern_addr, CHR_RENDERED);
+                    self.bg_shifter.set_next_pattern_lo(pattern);
+                }
+                6 => {
+                    let pattern_addr = self.bg_pattern_addr() + 8;
+                    let pattern = self.internal_read_vram(pattern_addr);
+                    self.log_chr(pattern_addr, CHR_RENDERED);
+                    self.bg_shifter.set_next_pattern_hi(pattern);
+                }
+                7 => self.internal_regs.coarse_x_inc(),
+                _ => {}
+            }
+        }
 
-    //         // Use the 'fine_x' we calculated (not the old one)
-    //         let mut palette_addr = (self.internal_read_vram(pattern_addr) >> (7 ^ fine_x)) & 1;
-    //         palette_addr |= ((self.internal_read_vram(pattern_addr + 8) >> (7 ^ fine_x)) & 1) << 1;
+        match cycle {
+            DOT_257_IN_SCANLINE => self.bg_shifter.load(),
+            // unused nametable fetches at the end of the line
+            DOT_338_IN_SCANLINE | DOT_340_IN_SCANLINE => {
+                let tile_id = self.internal_read_vram(self.internal_regs.fetch_tile_addr());
+                self.bg_shifter.set_next_tile_id(tile_id);
+            }
+            _ => {}
+        }
+    }
 
-    //         if palette_addr != 0 {
-    //             let attr = self.internal_read_vram(attr_addr);
-    //             // Use 'pixel_v' for attribute quadrant calculation
-    //             res = palette_addr as u16
-    //                 | (((attr as u16 >> ((pixel_v >> 4) & 4 | pixel_v & 2)) & 0x3) << 2);
-    //         }
-    //     }
-    //     res
-    // }
+    fn bg_pattern_addr(&self) -> u16 {
+        let fine_y = (self.internal_regs.get_v() & FINE_Y_SCROLL) >> 12;
+        (self.bg_shifter.next_tile_id() as u16 * 16 + fine_y) | self.ctrl_reg.bknd_pattern_addr()
+    }
 
     fn render_sprites(&mut self, bg_addr: u16, back_priority: &mut u8) -> u16 {
         let x = self.cycles as u16 - 1;
-        let y = self.scanline;
         let mut palette_addr = 0;
-        let len = self.ctrl_reg.sprite_size();
-        let mut j = 0;
-
-        while (palette_addr == 0) && (j < self.oam_cache_len) {
-            let i = self.oam_cache[j as usize] as usize;
-            let tile_x = self.oam_data[i + 3] as u16;
-
-            if !((x < tile_x) || ((x - tile_x) >= 8)) {
-                let tile = self.oam_data[i + 1] as u16;
-                let tile_y = self.oam_data[i] as u16 + 1;
-                let attr = self.oam_data[i + 2];
-                let mut x_off = (x as i32 - tile_x as i32) & (8 - 1);
-                let mut y_off = (y as i32 - tile_y as i32) & (len as u16 - 1) as i32;
-
-                if (attr & FLIP_HORIZONTAL) == 0 {
-                    x_off ^= 7;
-                }
-                if (attr & FLIP_VERTICAL) != 0 {
-                    y_off ^= len as i32 - 1;
-                }
 
-                let mut tile_addr;
-
-                if self.ctrl_reg.sprite_size() == 16 {
-                    y_off = (y_off & 7) | ((y_off & 8) << 1);
-                    tile_addr = (tile >> 1) * 32 + y_off as u16;
-                    tile_addr |= (tile & 1) << 12;
-                } else {
-                    tile_addr = tile * 16 + y_off as u16 + (self.ctrl_reg.sprt_pattern_addr());
-                }
-
-                palette_addr = (self.internal_read_vram(tile_addr) as u16 >> x_off) & 1;
-                palette_addr |= ((self.internal_read_vram(tile_addr + 8) as u16 >> x_off) & 1) << 1;
+        for (slot, unit) in self.sprite_units.iter().enumerate() {
+            let offset = x.wrapping_sub(unit.x as u16);
+            if offset >= 8 {
+                continue;
+            }
 
-                if palette_addr != 0 {
-                    palette_addr |= 0x10 | ((attr as u16 & 0x3) << 2);
-                    *back_priority = attr & (1 << 5);
+            let bit = 7 - offset;
+            palette_addr = ((unit.pattern_lo as u16 >> bit) & 1)
+                | (((unit.pattern_hi as u16 >> bit) & 1) << 1);
+            if palette_addr == 0 {
+                continue;
+            }
 
-                    if !(self.status_reg.is_sprite_0_hit())
-                        && (self.mask_reg.show_background())
-                        && (i == 0)
-                        && (palette_addr != 0)
-                        && (bg_addr != 0)
-                        && (x < 255)
-                    {
-                        self.status_reg.set_sprite_zero_hit();
-                    }
-                }
+            palette_addr |= 0x10 | ((unit.attr as u16 & 0x3) << 2);
+            *back_priority = unit.attr & (1 << 5);
+
+            if !(self.status_reg.is_sprite_0_hit())
+                && (self.mask_reg.show_background())
+                && (slot == 0)
+                && self.sprite_zero_in_units
+                && (bg_addr != 0)
+                && (x < 255)
+            {
+                self.status_reg.set_sprite_zero_hit();
             }
-            j += 1;
+            if self.layers.is_sprite_hidden(unit.oam_index) {
+                palette_addr = 0;
+                continue;
+            }
+            break;
         }
 
         palette_addr
     }
 }
+
+#[cfg(test)]
+mod test;
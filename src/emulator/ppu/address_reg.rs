@@ -1,4 +1,4 @@
-use super::{MIRRORS_ADDR, control_reg::AddressInc};
+use super::control_reg::AddressInc;
 
 pub struct AddressReg {
     value: (u8, u8),
@@ -40,9 +40,6 @@ impl AddressReg {
     }
 
     fn mirror_down_addr(&mut self) {
-        let value = self.get();
-        if value >= MIRRORS_ADDR {
-            self.set(value & MIRRORS_MASK);
-        }
+        self.set(self.get() & MIRRORS_MASK);
     }
 }
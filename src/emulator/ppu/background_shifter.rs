@@ -0,0 +1,95 @@
+pub struct BackgroundShifter {
+    pattern_lo: u16,
+    pattern_hi: u16,
+    attr_lo: u16,
+    attr_hi: u16,
+    next_tile_id: u8,
+    next_attr: u8,
+    next_pattern_lo: u8,
+    next_pattern_hi: u8,
+}
+
+const LOW_BYTE: u16 = 0x00FF;
+const HIGH_BYTE: u16 = 0xFF00;
+const PIXEL_MUX: u16 = 0x8000;
+const ATTR_BITS: u8 = 0b11;
+
+impl BackgroundShifter {
+    pub fn new() -> Self {
+        BackgroundShifter {
+            pattern_lo: 0,
+            pattern_hi: 0,
+            attr_lo: 0,
+            attr_hi: 0,
+            next_tile_id: 0,
+            next_attr: 0,
+            next_pattern_lo: 0,
+            next_pattern_hi: 0,
+        }
+    }
+
+    pub fn next_tile_id(&self) -> u8 {
+        self.next_tile_id
+    }
+
+    pub fn set_next_tile_id(&mut self, tile_id: u8) {
+        self.next_tile_id = tile_id;
+    }
+
+    // the attribute byte covers 4x4 tiles, coarse x/y bit 1 select the 2x2 quadrant
+    pub fn set_next_attr(&mut self, attr: u8, coarse_x: u16, coarse_y: u16) {
+        let shift = ((coarse_y & 0b10) << 1) | (coarse_x & 0b10);
+        self.next_attr = (attr >> shift) & ATTR_BITS;
+    }
+
+    pub fn set_next_pattern_lo(&mut self, pattern: u8) {
+        self.next_pattern_lo = pattern;
+    }
+
+    pub fn set_next_pattern_hi(&mut self, pattern: u8) {
+        self.next_pattern_hi = pattern;
+    }
+
+    // the fetched tile enters the low byte and reaches the output bit 8 shifts later
+    pub fn load(&mut self) {
+        self.pattern_lo = (self.pattern_lo & HIGH_BYTE) | self.next_pattern_lo as u16;
+        self.pattern_hi = (self.pattern_hi & HIGH_BYTE) | self.next_pattern_hi as u16;
+
+        let attr_lo = if (self.next_attr & 0b01) != 0 {
+            LOW_BYTE
+        } else {
+            0
+        };
+        let attr_hi = if (self.next_attr & 0b10) != 0 {
+            LOW_BYTE
+        } else {
+            0
+        };
+        self.attr_lo = (self.attr_lo & HIGH_BYTE) | attr_lo;
+        self.attr_hi = (self.attr_hi & HIGH_BYTE) | attr_hi;
+    }
+
+    pub fn shift(&mut self) {
+        self.pattern_lo <<= 1;
+        self.pattern_hi <<= 1;
+        self.attr_lo <<= 1;
+        self.attr_hi <<= 1;
+    }
+
+    // returns the 4 bit background palette index, 0 when the pixel is transparent
+    pub fn pixel(&self, fine_x: u8) -> u8 {
+        let mux = PIXEL_MUX >> fine_x;
+        let bit = |reg: u16| ((reg & mux) != 0) as u8;
+
+        let pattern = (bit(self.pattern_hi) << 1) | bit(self.pattern_lo);
+        if pattern == 0 {
+            return 0;
+        }
+
+        let palette = (bit(self.attr_hi) << 1) | bit(self.attr_lo);
+        (palette << 2) | pattern
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,136 @@
+use super::*;
+
+const TILE_LO: u8 = 0b1010_1010;
+const TILE_HI: u8 = 0b1100_1100;
+
+fn loaded(pattern_lo: u8, pattern_hi: u8, attr: u8) -> BackgroundShifter {
+    let mut shifter = BackgroundShifter::new();
+    shifter.set_next_pattern_lo(pattern_lo);
+    shifter.set_next_pattern_hi(pattern_hi);
+    shifter.set_next_attr(attr, 0, 0);
+    shifter.load();
+    shifter
+}
+
+fn tile_pattern(bit: u8) -> u8 {
+    (((TILE_HI >> (7 - bit)) & 1) << 1) | ((TILE_LO >> (7 - bit)) & 1)
+}
+
+#[test]
+fn loaded_tile_reaches_the_output_after_eight_shifts() {
+    let mut shifter = loaded(TILE_LO, TILE_HI, 0);
+
+    for _ in 0..7 {
+        shifter.shift();
+        assert_eq!(shifter.pixel(0), 0);
+    }
+    for bit in 0..8 {
+        shifter.shift();
+        assert_eq!(shifter.pixel(0), tile_pattern(bit), "bit {bit}");
+    }
+}
+
+#[test]
+fn load_keeps_the_tile_being_drawn() {
+    let mut shifter = loaded(0xFF, 0x00, 0);
+    for _ in 0..8 {
+        shifter.shift();
+    }
+    shifter.set_next_pattern_lo(0x00);
+    shifter.set_next_pattern_hi(0xFF);
+    shifter.load();
+
+    for _ in 0..8 {
+        assert_eq!(shifter.pixel(0), 0b01);
+        shifter.shift();
+    }
+    for _ in 0..8 {
+        assert_eq!(shifter.pixel(0), 0b10);
+        shifter.shift();
+    }
+    assert_eq!(shifter.pixel(0), 0);
+}
+
+#[test]
+fn fine_x_selects_the_pixel() {
+    let mut shifter = loaded(TILE_LO, TILE_HI, 0);
+    for _ in 0..8 {
+        shifter.shift();
+    }
+
+    for fine_x in 0..8 {
+        assert_eq!(
+            shifter.pixel(fine_x),
+            tile_pattern(fine_x),
+            "fine x {fine_x}"
+        );
+    }
+}
+
+#[test]
+fn fine_x_reaches_into_the_next_tile() {
+    let mut shifter = loaded(0x00, 0x00, 0);
+    for _ in 0..8 {
+        shifter.shift();
+    }
+    shifter.set_next_pattern_lo(0x80);
+    shifter.set_next_pattern_hi(0x00);
+    shifter.load();
+
+    for _ in 0..7 {
+        shifter.shift();
+    }
+    assert_eq!(shifter.pixel(0), 0);
+    assert_eq!(shifter.pixel(1), 0b01);
+}
+
+#[test]
+fn attribute_quadrant_follows_coarse_scroll() {
+    let attr = 0b11_10_01_00;
+    let quadrants = [
+        ((0, 0), 0),
+        ((1, 1), 0),
+        ((2, 0), 1),
+        ((0, 2), 2),
+        ((3, 3), 3),
+    ];
+
+    for ((coarse_x, coarse_y), palette) in quadrants {
+        let mut shifter = BackgroundShifter::new();
+        shifter.set_next_pattern_lo(0xFF);
+        shifter.set_next_attr(attr, coarse_x, coarse_y);
+        shifter.load();
+        for _ in 0..8 {
+            shifter.shift();
+        }
+        assert_eq!(
+            shifter.pixel(0),
+            (palette << 2) | 0b01,
+            "tile {coarse_x},{coarse_y}"
+        );
+    }
+}
+
+#[test]
+fn attribute_bits_cover_the_whole_tile() {
+    let mut shifter = loaded(0xFF, 0x00, 0b10);
+    for _ in 0..8 {
+        shifter.shift();
+    }
+
+    for fine_x in 0..8 {
+        assert_eq!(shifter.pixel(fine_x), (0b10 << 2) | 0b01, "fine x {fine_x}");
+    }
+}
+
+#[test]
+fn transparent_pixels_ignore_the_attribute() {
+    let mut shifter = loaded(0x00, 0x00, 0b11);
+    for _ in 0..8 {
+        shifter.shift();
+    }
+
+    for fine_x in 0..8 {
+        assert_eq!(shifter.pixel(fine_x), 0);
+    }
+}
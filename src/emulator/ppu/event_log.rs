@@ -0,0 +1,44 @@
+// CPU writes to PPU and mapper registers, stamped with the PPU position they landed on
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PpuEvent {
+    pub scanline: u16,
+    pub dot: usize,
+    pub addr: u16,
+    pub value: u8,
+}
+
+#[derive(Default)]
+pub struct EventLog {
+    current: Vec<PpuEvent>,
+    previous: Vec<PpuEvent>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        EventLog::default()
+    }
+
+    pub fn record(&mut self, event: PpuEvent) {
+        self.current.push(event);
+    }
+
+    // frames are split at the start of vblank, so a frame keeps the writes made while it was drawn
+    pub fn end_frame(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    pub fn get_events(&self) -> &[PpuEvent] {
+        &self.previous
+    }
+
+    pub fn events_near(&self, scanline: u16, dot: usize, radius: usize) -> Vec<PpuEvent> {
+        self.previous
+            .iter()
+            .filter(|event| {
+                (event.scanline as usize).abs_diff(scanline as usize) <= radius
+                    && event.dot.abs_diff(dot) <= radius
+            })
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,38 @@
+// the data bus between the CPU and the PPU registers holds its charge for a while,
+// every bit fades to 0 on its own around 600ms after it was last driven
+pub const DECAY_FRAMES: usize = 36;
+pub const FULL_BYTE: u8 = 0xFF;
+
+const BITS: usize = 8;
+
+pub struct IoLatch {
+    value: u8,
+    refreshed: [usize; BITS],
+}
+
+impl IoLatch {
+    pub fn new() -> Self {
+        IoLatch {
+            value: 0,
+            refreshed: [0; BITS],
+        }
+    }
+
+    // only the bits in mask are driven, the rest keep their old charge
+    pub fn refresh(&mut self, value: u8, mask: u8, frame: usize) {
+        self.value = (self.value & !mask) | (value & mask);
+        for (bit, refreshed) in self.refreshed.iter_mut().enumerate() {
+            if (mask & (1 << bit)) != 0 {
+                *refreshed = frame;
+            }
+        }
+    }
+
+    pub fn get(&self, frame: usize) -> u8 {
+        self.refreshed
+            .iter()
+            .enumerate()
+            .filter(|(_, refreshed)| frame.saturating_sub(**refreshed) < DECAY_FRAMES)
+            .fold(0, |value, (bit, _)| value | (self.value & (1 << bit)))
+    }
+}
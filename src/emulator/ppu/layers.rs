@@ -0,0 +1,36 @@
+pub const NUM_OF_SPRITES: u8 = 64;
+
+// frontend overrides on top of PPUMASK, they only change what ends up on screen
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Layers {
+    pub hide_background: bool,
+    pub hide_sprites: bool,
+    // one bit per OAM index
+    hidden_sprites: u64,
+}
+
+impl Layers {
+    pub fn hide_sprite(&mut self, index: u8) {
+        self.hidden_sprites |= Self::sprite_bit(index);
+    }
+
+    pub fn show_sprite(&mut self, index: u8) {
+        self.hidden_sprites &= !Self::sprite_bit(index);
+    }
+
+    pub fn toggle_sprite(&mut self, index: u8) {
+        if (self.hidden_sprites & Self::sprite_bit(index)) != 0 {
+            self.show_sprite(index);
+        } else {
+            self.hide_sprite(index);
+        }
+    }
+
+    pub fn is_sprite_hidden(&self, index: u8) -> bool {
+        self.hide_sprites || (self.hidden_sprites & Self::sprite_bit(index)) != 0
+    }
+
+    fn sprite_bit(index: u8) -> u64 {
+        1 << (index & 0x3F)
+    }
+}
@@ -11,6 +11,8 @@ pub const EMPHASIZE_RED_FLAG: u8 = 0b0010_0000;
 pub const EMPHASIZE_GREEN_FLAG: u8 = 0b0100_0000;
 pub const EMPHASIZE_BKUE_FLAG: u8 = 0b1000_0000;
 
+const EMPHASIS_SHIFT: u8 = 5;
+
 impl MaskReg {
     pub fn new() -> Self {
         MaskReg { flags: 0 }
@@ -39,4 +41,14 @@ impl MaskReg {
     pub fn show_background_8(&self) -> bool {
         (self.flags & SHOW_BACKGROUND_IN_LEFT_8_PIXELS_FLAG) != 0
     }
+
+    pub fn is_greyscale(&self) -> bool {
+        (self.flags & GREY_SCALE_FLAG) != 0
+    }
+
+    // bgr emphasis bits, bit 0 is red
+    pub fn emphasis(&self) -> u8 {
+        (self.flags & (EMPHASIZE_RED_FLAG | EMPHASIZE_GREEN_FLAG | EMPHASIZE_BKUE_FLAG))
+            >> EMPHASIS_SHIFT
+    }
 }
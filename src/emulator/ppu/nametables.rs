@@ -0,0 +1,95 @@
+use crate::emulator::rom::Mirroring;
+
+pub const NAME_TABLE_SIZE: usize = 0x0400;
+pub const NUM_OF_NAME_TABLES: usize = 4;
+// four-screen boards carry another 2KB of nametable RAM
+pub const FOUR_SCREEN_VRAM_SIZE: usize = 2 * NAME_TABLE_SIZE;
+
+const CIRAM_SIZE: usize = 2 * NAME_TABLE_SIZE;
+// $2000-$3EFF, where $3000 mirrors $2000
+const NAME_TABLES_MASK: u16 = 0b0000_1111_1111_1111;
+const TABLE_SHIFT: u16 = 10;
+
+// 1KB page backing one of the four logical nametables
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NametableBank {
+    // the 2KB inside the console
+    Ciram(usize),
+    // memory supplied by the cartridge
+    Cartridge(usize),
+}
+
+pub struct Nametables {
+    ciram: [u8; CIRAM_SIZE],
+    cartridge_vram: Vec<u8>,
+    banks: [NametableBank; NUM_OF_NAME_TABLES],
+}
+
+impl Nametables {
+    pub fn new(mirroring: Mirroring, cartridge_vram: Vec<u8>) -> Self {
+        let mut nametables = Nametables {
+            ciram: [0; CIRAM_SIZE],
+            cartridge_vram,
+            banks: [NametableBank::Ciram(0); NUM_OF_NAME_TABLES],
+        };
+        nametables.set_mirroring(mirroring);
+        nametables
+    }
+
+    pub fn set_mirroring(&mut self, mirroring: Mirroring) {
+        use NametableBank::*;
+
+        self.banks = match mirroring {
+            Mirroring::Vertical => [Ciram(0), Ciram(1), Ciram(0), Ciram(1)],
+            Mirroring::Horizontal => [Ciram(0), Ciram(0), Ciram(1), Ciram(1)],
+            Mirroring::FourScreen => [Ciram(0), Ciram(1), Cartridge(0), Cartridge(1)],
+        };
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        let (table, offset) = Self::split_addr(addr);
+        self.get_table(table)[offset]
+    }
+
+    pub fn write(&mut self, addr: u16, value: u8) {
+        let (table, offset) = Self::split_addr(addr);
+        let range = self.bank_range(table);
+
+        match self.banks[table] {
+            NametableBank::Ciram(_) => self.ciram[range][offset] = value,
+            NametableBank::Cartridge(_) => {
+                // writes to missing cartridge memory are lost
+                if let Some(page) = self.cartridge_vram.get_mut(range) {
+                    page[offset] = value;
+                }
+            }
+        }
+    }
+
+    pub fn get_table(&self, table: usize) -> &[u8] {
+        const OPEN_PAGE: [u8; NAME_TABLE_SIZE] = [0; NAME_TABLE_SIZE];
+
+        let table = table % NUM_OF_NAME_TABLES;
+        let range = self.bank_range(table);
+        match self.banks[table] {
+            NametableBank::Ciram(_) => &self.ciram[range],
+            NametableBank::Cartridge(_) => self.cartridge_vram.get(range).unwrap_or(&OPEN_PAGE),
+        }
+    }
+
+    fn bank_range(&self, table: usize) -> std::ops::Range<usize> {
+        let page = match self.banks[table] {
+            NametableBank::Ciram(page) => page % (CIRAM_SIZE / NAME_TABLE_SIZE),
+            NametableBank::Cartridge(page) => page,
+        };
+        page * NAME_TABLE_SIZE..(page + 1) * NAME_TABLE_SIZE
+    }
+
+    fn split_addr(addr: u16) -> (usize, usize) {
+        let index = addr & NAME_TABLES_MASK;
+        (
+            (index >> TABLE_SHIFT) as usize,
+            index as usize & (NAME_TABLE_SIZE - 1),
+        )
+    }
+}
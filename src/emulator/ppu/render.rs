@@ -1,12 +1,18 @@
+pub mod debug;
+pub mod export;
 pub mod frame;
+pub mod framing;
+pub mod ntsc;
+pub mod palette;
 pub mod pallete_table;
+pub mod png;
+pub mod post;
 mod rect;
 
 use core::panic;
 
-use crate::emulator::rom::Mirroring;
-
-use super::{NAME_TABLE_SIZE, PALETTE_TABLE_SIZE, Ppu, VRAM_ADDR};
+use super::nametables::NAME_TABLE_SIZE;
+use super::{PALETTE_TABLE_SIZE, Ppu, VRAM_ADDR};
 use frame::Frame;
 use pallete_table::SYSTEM_PALLETE;
 use rect::Rect;
@@ -29,10 +35,10 @@ const START_SPRITE_PALETTE_OFFSET: u8 = 0x11;
 const NUM_OF_SPRITE_PALETTES: usize = 4;
 
 const ATTRIBUTE_OFFSET: usize = 0x03C0;
-const TWO_NAMETABLE_SIZE: u16 = NAME_TABLE_SIZE * 2;
-const THREE_NAMETABLE_SIZE: u16 = NAME_TABLE_SIZE * 3;
+const TWO_NAMETABLE_SIZE: u16 = NAME_TABLE_SIZE as u16 * 2;
+const THREE_NAMETABLE_SIZE: u16 = NAME_TABLE_SIZE as u16 * 3;
 const FIRST_TABLE_ADDR: u16 = VRAM_ADDR;
-const SECOND_TABLE_ADDR: u16 = VRAM_ADDR + NAME_TABLE_SIZE;
+const SECOND_TABLE_ADDR: u16 = VRAM_ADDR + NAME_TABLE_SIZE as u16;
 const THIRD_TABLE_ADDR: u16 = VRAM_ADDR + TWO_NAMETABLE_SIZE;
 const FORTH_TABLE_ADDR: u16 = VRAM_ADDR + THREE_NAMETABLE_SIZE;
 
@@ -47,7 +53,8 @@ pub fn render(ppu: &Ppu, frame: &mut Frame) {
 fn draw_backgound(ppu: &Ppu, frame: &mut Frame) {
     let scroll_x = ppu.internal_regs.get_scroll_x() as usize;
     let scroll_y = ppu.internal_regs.get_scroll_y() as usize;
-    let base_nametable = VRAM_ADDR + ppu.internal_regs.get_nametable_select() * NAME_TABLE_SIZE;
+    let base_nametable =
+        VRAM_ADDR + ppu.internal_regs.get_nametable_select() * NAME_TABLE_SIZE as u16;
 
     println!("scroll x: {scroll_x}, scroll y: {scroll_y}");
 
@@ -163,42 +170,12 @@ fn bg_pallete(
     ]
 }
 
+// the base nametable followed by its right, bottom and diagonal neighbours
 fn get_nametables(ppu: &Ppu, base_addr: u16) -> (&[u8], &[u8], &[u8], &[u8]) {
-    use Mirroring::*;
-
-    match ppu.mirroring {
-        Vertical => match base_addr {
-            FIRST_TABLE_ADDR | THIRD_TABLE_ADDR => (
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-            ),
-            SECOND_TABLE_ADDR | FORTH_TABLE_ADDR => (
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-            ),
-            _ => panic!("invalid nametable address for vertical mirroring"),
-        },
-        Horizontal => match base_addr {
-            FIRST_TABLE_ADDR | SECOND_TABLE_ADDR => (
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-            ),
-            THIRD_TABLE_ADDR | FORTH_TABLE_ADDR => (
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-                &ppu.vram[NAME_TABLE_SIZE as usize..TWO_NAMETABLE_SIZE as usize],
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-                &ppu.vram[0..NAME_TABLE_SIZE as usize],
-            ),
-            _ => panic!("invalid nametable address for vertical mirroring"),
-        },
-        FourScreen => panic!("four screen not implemented yet"),
-    }
+    let base = (base_addr - VRAM_ADDR) as usize / NAME_TABLE_SIZE;
+    let table = |i: usize| ppu.nametables.get_table(base ^ i);
+
+    (table(0), table(1), table(2), table(3))
 }
 
 fn draw_sprites(ppu: &Ppu, frame: &mut Frame) {
@@ -277,7 +254,7 @@ fn render_name_table(
     shift_y: isize,
 ) {
     let bank = ppu.ctrl_reg.bknd_pattern_addr();
-    let attribute_table = &name_table[ATTRIBUTE_OFFSET..NAME_TABLE_SIZE as usize];
+    let attribute_table = &name_table[ATTRIBUTE_OFFSET..NAME_TABLE_SIZE];
 
     for i in 0..ATTRIBUTE_OFFSET {
         let tile_col = i & (PALETTE_TABLE_SIZE - 1);
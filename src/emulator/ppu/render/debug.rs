@@ -0,0 +1,385 @@
+use super::frame::Frame;
+use super::post::Image;
+use crate::emulator::ppu::event_log::PpuEvent;
+use crate::emulator::ppu::{CYCLES_PER_SCANLINE, Ppu, SCANLINES_PER_FRAME};
+
+pub const NAMETABLES_WIDTH: usize = 2 * Frame::WIDTH;
+pub const NAMETABLES_HEIGHT: usize = 2 * Frame::HIGHT;
+pub const PATTERN_TABLES_WIDTH: usize = 2 * PATTERN_TABLE_SIZE;
+pub const PATTERN_TABLES_HEIGHT: usize = PATTERN_TABLE_SIZE;
+pub const OAM_WIDTH: usize = OAM_COLUMNS * OAM_CELL_WIDTH;
+pub const OAM_HEIGHT: usize = OAM_ROWS * OAM_CELL_HEIGHT;
+pub const PALETTES_WIDTH: usize = PALETTE_COLUMNS * SWATCH_SIZE;
+pub const PALETTES_HEIGHT: usize = PALETTE_ROWS * SWATCH_SIZE;
+pub const NUM_OF_PALETTES: usize = 8;
+pub const COLORS_PER_PALETTE: usize = 4;
+pub const CHR_BANK_SIZE: usize = PATTERN_TABLE_SIZE;
+pub const CHR_BANK_BYTES: usize = PATTERN_TABLE_BYTES;
+pub const EVENTS_WIDTH: usize = CYCLES_PER_SCANLINE;
+pub const EVENTS_HEIGHT: usize = SCANLINES_PER_FRAME as usize;
+
+const TILE_SIZE: usize = 8;
+const TILE_BYTES: usize = 16;
+const TILES_PER_ROW: usize = 32;
+const TILE_ROWS: usize = 30;
+const ATTRIBUTE_OFFSET: usize = 0x3C0;
+const PATTERN_TABLE_SIZE: usize = 128;
+const PATTERN_TABLE_BYTES: usize = 0x1000;
+const TILES_PER_PATTERN_ROW: usize = 16;
+
+const NUM_OF_SPRITES: usize = 64;
+const SPRITE_BYTES: usize = 4;
+const OAM_COLUMNS: usize = 8;
+const OAM_ROWS: usize = 8;
+const OAM_CELL_WIDTH: usize = 16;
+const OAM_CELL_HEIGHT: usize = 24;
+// sprites at these lines are off screen
+const HIDDEN_SPRITE_Y: u8 = 0xEF;
+const TALL_TILE_BANK: u8 = 0b0000_0001;
+const FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const FLIP_VERTICAL: u8 = 0b1000_0000;
+const SPRITE_PALETTE_MASK: u8 = 0b0000_0011;
+const SPRITE_PALETTES: usize = 4;
+
+const PALETTE_COLUMNS: usize = 16;
+const PALETTE_ROWS: usize = 2;
+const SWATCH_SIZE: usize = 16;
+
+const SCROLL_COLOR: u32 = 0xFF00FF;
+const CELL_COLOR: u32 = 0x202020;
+
+const MARKER_RADIUS: usize = 1;
+const MAPPER_REGISTERS: u16 = 0x8000;
+const OAM_DMA_REGISTER: u16 = 0x4014;
+const PPU_REGISTER_MASK: u16 = 0b0111;
+const PPU_REGISTER_NAMES: [&str; 8] = [
+    "PPUCTRL",
+    "PPUMASK",
+    "PPUSTATUS",
+    "OAMADDR",
+    "OAMDATA",
+    "PPUSCROLL",
+    "PPUADDR",
+    "PPUDATA",
+];
+const PPU_REGISTER_COLORS: [u32; 8] = [
+    0xFF4040, 0x40FF40, 0x808080, 0xFF9020, 0xFFFF40, 0x40FFFF, 0x4080FF, 0xC040FF,
+];
+const OAM_DMA_COLOR: u32 = 0xFFFFFF;
+const MAPPER_COLOR: u32 = 0xFF80C0;
+
+// all four nametables as the PPU would draw them, with the visible area outlined
+pub fn nametables(ppu: &Ppu) -> Image {
+    let mut image = nametable_map(ppu);
+    outline_scroll(&mut image, ppu.get_scroll());
+    image
+}
+
+// all four nametables as the PPU would draw them
+pub fn nametable_map(ppu: &Ppu) -> Image {
+    let mut image = Image::new(NAMETABLES_WIDTH, NAMETABLES_HEIGHT);
+    let bank = ppu.get_bg_pattern_addr() as usize;
+
+    for table in 0..4 {
+        let nametable = ppu.get_nametables().get_table(table);
+        let left = (table & 1) * Frame::WIDTH;
+        let top = (table >> 1) * Frame::HIGHT;
+
+        for row in 0..TILE_ROWS {
+            for col in 0..TILES_PER_ROW {
+                let tile = nametable[row * TILES_PER_ROW + col] as usize;
+                let attr = nametable[ATTRIBUTE_OFFSET + (row / 4) * 8 + col / 4];
+                let shift = ((row & 2) << 1) | (col & 2);
+                let palette = ((attr >> shift) & 0b11) as usize;
+
+                let colors = palette_colors(ppu, palette);
+                let tile = TileRef {
+                    addr: bank + tile * TILE_BYTES,
+                    flip_h: false,
+                    flip_v: false,
+                };
+                draw_tile(
+                    &mut image,
+                    ppu.get_chr(),
+                    tile,
+                    left + col * TILE_SIZE,
+                    top + row * TILE_SIZE,
+                    &colors,
+                    false,
+                );
+            }
+        }
+    }
+    image
+}
+
+// both pattern tables side by side, coloured with one of the 8 palettes
+pub fn pattern_tables(ppu: &Ppu, palette: usize) -> Image {
+    let mut image = Image::new(PATTERN_TABLES_WIDTH, PATTERN_TABLES_HEIGHT);
+    let colors = palette_colors(ppu, palette % NUM_OF_PALETTES);
+
+    for table in 0..2 {
+        for tile in 0..(PATTERN_TABLE_BYTES / TILE_BYTES) {
+            let x = table * PATTERN_TABLE_SIZE + (tile % TILES_PER_PATTERN_ROW) * TILE_SIZE;
+            let y = (tile / TILES_PER_PATTERN_ROW) * TILE_SIZE;
+            let tile = TileRef {
+                addr: table * PATTERN_TABLE_BYTES + tile * TILE_BYTES,
+                flip_h: false,
+                flip_v: false,
+            };
+            draw_tile(&mut image, ppu.get_chr(), tile, x, y, &colors, false);
+        }
+    }
+    image
+}
+
+// one 4KB bank of raw CHR data, 16x16 tiles
+pub fn chr_bank(chr: &[u8], bank: usize, colors: &[u32; COLORS_PER_PALETTE]) -> Image {
+    let mut image = Image::new(CHR_BANK_SIZE, CHR_BANK_SIZE);
+
+    for tile in 0..(CHR_BANK_BYTES / TILE_BYTES) {
+        let x = (tile % TILES_PER_PATTERN_ROW) * TILE_SIZE;
+        let y = (tile / TILES_PER_PATTERN_ROW) * TILE_SIZE;
+        let tile = TileRef {
+            addr: bank * CHR_BANK_BYTES + tile * TILE_BYTES,
+            flip_h: false,
+            flip_v: false,
+        };
+        draw_tile(&mut image, chr, tile, x, y, colors, false);
+    }
+    image
+}
+
+// the 64 sprites in OAM order, each in its own cell
+pub fn oam(ppu: &Ppu) -> Image {
+    let mut image = Image::new(OAM_WIDTH, OAM_HEIGHT);
+    let oam = ppu.get_oam();
+    let height = ppu.get_sprite_height() as usize;
+
+    for sprite in 0..NUM_OF_SPRITES {
+        let [y, tile, attr, _] = sprite_bytes(oam, sprite);
+        let cell_x = (sprite % OAM_COLUMNS) * OAM_CELL_WIDTH;
+        let cell_y = (sprite / OAM_COLUMNS) * OAM_CELL_HEIGHT;
+
+        for py in 0..OAM_CELL_HEIGHT {
+            for px in 0..OAM_CELL_WIDTH {
+                image.set_pixel(cell_x + px, cell_y + py, CELL_COLOR);
+            }
+        }
+        if y >= HIDDEN_SPRITE_Y {
+            continue;
+        }
+
+        let palette = SPRITE_PALETTES + (attr & SPRITE_PALETTE_MASK) as usize;
+        let colors = palette_colors(ppu, palette);
+        let flip_h = (attr & FLIP_HORIZONTAL) != 0;
+        let flip_v = (attr & FLIP_VERTICAL) != 0;
+        let x = cell_x + (OAM_CELL_WIDTH - TILE_SIZE) / 2;
+        let y = cell_y + (OAM_CELL_HEIGHT - height) / 2;
+
+        for (half, addr) in sprite_tiles(ppu, tile)
+            .into_iter()
+            .enumerate()
+            .take(height / TILE_SIZE)
+        {
+            // a vertically flipped 8x16 sprite also swaps its two tiles
+            let half = if flip_v && height > TILE_SIZE {
+                1 - half
+            } else {
+                half
+            };
+            let tile = TileRef {
+                addr,
+                flip_h,
+                flip_v,
+            };
+            draw_tile(
+                &mut image,
+                ppu.get_chr(),
+                tile,
+                x,
+                y + half * TILE_SIZE,
+                &colors,
+                true,
+            );
+        }
+    }
+    image
+}
+
+// the OAM index of the cell under a point of the oam() image
+pub fn oam_sprite_at(x: usize, y: usize) -> Option<u8> {
+    if (x >= OAM_WIDTH) || (y >= OAM_HEIGHT) {
+        return None;
+    }
+    Some(((y / OAM_CELL_HEIGHT) * OAM_COLUMNS + x / OAM_CELL_WIDTH) as u8)
+}
+
+// palette RAM, background palettes on the top row and sprite palettes below
+pub fn palettes(ppu: &Ppu) -> Image {
+    let mut image = Image::new(PALETTES_WIDTH, PALETTES_HEIGHT);
+    let table = ppu.get_palette_table();
+
+    for (entry, color) in table.iter().enumerate() {
+        let rgb = pack(ppu.get_palette().color(*color, 0));
+        let left = (entry % PALETTE_COLUMNS) * SWATCH_SIZE;
+        let top = (entry / PALETTE_COLUMNS) * SWATCH_SIZE;
+
+        for y in 0..SWATCH_SIZE {
+            for x in 0..SWATCH_SIZE {
+                image.set_pixel(left + x, top + y, rgb);
+            }
+        }
+    }
+    image
+}
+
+// the last frame on a dot by scanline grid, every register write marked where it landed
+pub fn events(ppu: &Ppu) -> Image {
+    let mut image = Image::new(EVENTS_WIDTH, EVENTS_HEIGHT);
+    let screen = Image::from_frame(&ppu.screen);
+
+    for y in 0..Frame::HIGHT {
+        for x in 0..Frame::WIDTH {
+            // dot 0 is idle, pixel 0 comes out on dot 1
+            image.set_pixel(x + 1, y, screen.get_pixel(x, y));
+        }
+    }
+
+    let events = ppu
+        .get_event_log()
+        .map(|log| log.get_events())
+        .unwrap_or_default();
+    for event in events {
+        let color = event_color(event.addr);
+        let top = (event.scanline as usize).saturating_sub(MARKER_RADIUS);
+        let left = event.dot.saturating_sub(MARKER_RADIUS);
+
+        for y in top..=(event.scanline as usize + MARKER_RADIUS).min(EVENTS_HEIGHT - 1) {
+            for x in left..=(event.dot + MARKER_RADIUS).min(EVENTS_WIDTH - 1) {
+                image.set_pixel(x, y, color);
+            }
+        }
+    }
+    image
+}
+
+pub fn describe_event(event: &PpuEvent) -> String {
+    let register = match event.addr {
+        OAM_DMA_REGISTER => "OAMDMA",
+        addr if addr >= MAPPER_REGISTERS => "mapper",
+        addr => PPU_REGISTER_NAMES[(addr & PPU_REGISTER_MASK) as usize],
+    };
+    format!(
+        "{}:{} {register} ${:04X}=${:02X}",
+        event.scanline, event.dot, event.addr, event.value
+    )
+}
+
+fn event_color(addr: u16) -> u32 {
+    match addr {
+        OAM_DMA_REGISTER => OAM_DMA_COLOR,
+        addr if addr >= MAPPER_REGISTERS => MAPPER_COLOR,
+        addr => PPU_REGISTER_COLORS[(addr & PPU_REGISTER_MASK) as usize],
+    }
+}
+
+pub fn sprite_bytes(oam: &[u8], sprite: usize) -> [u8; SPRITE_BYTES] {
+    let base = sprite * SPRITE_BYTES;
+    [oam[base], oam[base + 1], oam[base + 2], oam[base + 3]]
+}
+
+struct TileRef {
+    addr: usize,
+    flip_h: bool,
+    flip_v: bool,
+}
+
+fn sprite_tiles(ppu: &Ppu, tile: u8) -> [usize; 2] {
+    if ppu.get_sprite_height() as usize == TILE_SIZE {
+        let addr = ppu.get_sprite_pattern_addr() as usize + tile as usize * TILE_BYTES;
+        return [addr, addr + TILE_BYTES];
+    }
+
+    // 8x16 sprites pick the pattern table with bit 0 of the tile index
+    let bank = (tile & TALL_TILE_BANK) as usize * PATTERN_TABLE_BYTES;
+    let addr = bank + (tile & !TALL_TILE_BANK) as usize * TILE_BYTES;
+    [addr, addr + TILE_BYTES]
+}
+
+fn palette_colors(ppu: &Ppu, palette: usize) -> [u32; COLORS_PER_PALETTE] {
+    let table = ppu.get_palette_table();
+    std::array::from_fn(|i| {
+        // colour 0 of every palette is the shared backdrop
+        let entry = if i == 0 {
+            0
+        } else {
+            palette * COLORS_PER_PALETTE + i
+        };
+        pack(ppu.get_palette().color(table[entry], 0))
+    })
+}
+
+fn draw_tile(
+    image: &mut Image,
+    chr: &[u8],
+    tile: TileRef,
+    left: usize,
+    top: usize,
+    colors: &[u32; COLORS_PER_PALETTE],
+    transparent: bool,
+) {
+    let plane = |offset: usize| chr.get(tile.addr + offset).copied().unwrap_or(0);
+
+    for row in 0..TILE_SIZE {
+        let lo = plane(row);
+        let hi = plane(row + TILE_SIZE);
+        let y = if tile.flip_v {
+            TILE_SIZE - 1 - row
+        } else {
+            row
+        };
+
+        for bit in 0..TILE_SIZE {
+            let value = (((hi >> (7 - bit)) & 1) << 1) | ((lo >> (7 - bit)) & 1);
+            if transparent && value == 0 {
+                continue;
+            }
+            let x = if tile.flip_h {
+                TILE_SIZE - 1 - bit
+            } else {
+                bit
+            };
+            image.set_pixel(left + x, top + y, colors[value as usize]);
+        }
+    }
+}
+
+// the screen wraps around the nametables, so the outline may be split in pieces
+fn outline_scroll(image: &mut Image, (scroll_x, scroll_y): (usize, usize)) {
+    for offset in 0..Frame::WIDTH {
+        let x = (scroll_x + offset) % NAMETABLES_WIDTH;
+        image.set_pixel(x, scroll_y % NAMETABLES_HEIGHT, SCROLL_COLOR);
+        image.set_pixel(
+            x,
+            (scroll_y + Frame::HIGHT - 1) % NAMETABLES_HEIGHT,
+            SCROLL_COLOR,
+        );
+    }
+    for offset in 0..Frame::HIGHT {
+        let y = (scroll_y + offset) % NAMETABLES_HEIGHT;
+        image.set_pixel(scroll_x % NAMETABLES_WIDTH, y, SCROLL_COLOR);
+        image.set_pixel(
+            (scroll_x + Frame::WIDTH - 1) % NAMETABLES_WIDTH,
+            y,
+            SCROLL_COLOR,
+        );
+    }
+}
+
+pub(super) fn pack((r, g, b): (u8, u8, u8)) -> u32 {
+    u32::from_be_bytes([0, r, g, b])
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,125 @@
+use super::*;
+use crate::emulator::rom::Mirroring;
+use crate::emulator::test_helpers::{SOLID_TILE, reset_scroll, solid_tile_ppu, write_byte};
+
+const BACKDROP: u8 = 0x0F;
+const BG_COLOR: u8 = 0x21;
+const SPRITE_COLOR: u8 = 0x16;
+
+fn test_ppu() -> Ppu {
+    let mut ppu = solid_tile_ppu(Mirroring::Vertical);
+
+    write_byte(&mut ppu, 0x3F00, BACKDROP);
+    write_byte(&mut ppu, 0x3F01, BG_COLOR);
+    write_byte(&mut ppu, 0x3F11, SPRITE_COLOR);
+    reset_scroll(&mut ppu);
+    ppu
+}
+
+fn color(ppu: &Ppu, index: u8) -> u32 {
+    pack(ppu.get_palette().color(index, 0))
+}
+
+#[test]
+fn pattern_tables_use_the_selected_palette() {
+    let ppu = test_ppu();
+
+    let image = pattern_tables(&ppu, 0);
+    assert_eq!(image.get_pixel(0, 0), color(&ppu, BACKDROP));
+    assert_eq!(image.get_pixel(TILE_SIZE, 0), color(&ppu, BG_COLOR));
+
+    let image = pattern_tables(&ppu, 4);
+    assert_eq!(image.get_pixel(TILE_SIZE, 0), color(&ppu, SPRITE_COLOR));
+}
+
+#[test]
+fn nametables_show_mirrored_tables() {
+    let mut ppu = test_ppu();
+    // top left tile of the second nametable, mirrored into the fourth
+    write_byte(&mut ppu, 0x2400, SOLID_TILE);
+    reset_scroll(&mut ppu);
+
+    let image = nametables(&ppu);
+    let backdrop = color(&ppu, BACKDROP);
+    let tile = color(&ppu, BG_COLOR);
+    assert_eq!(image.get_pixel(Frame::WIDTH + 1, 1), tile);
+    assert_eq!(image.get_pixel(Frame::WIDTH + 1, Frame::HIGHT + 1), tile);
+    assert_eq!(image.get_pixel(1, Frame::HIGHT + 1), backdrop);
+}
+
+#[test]
+fn nametables_outline_the_scroll_position() {
+    let mut ppu = test_ppu();
+    ppu.write_to_ctrl(0b01);
+    ppu.write_to_scroll(16);
+    ppu.write_to_scroll(8);
+
+    let image = nametables(&ppu);
+    let (x, y) = (Frame::WIDTH + 16, 8);
+    assert_eq!(ppu.get_scroll(), (x, y));
+    assert_eq!(image.get_pixel(x, y + 10), SCROLL_COLOR);
+    // the right edge wraps around to the first nametable
+    assert_eq!(image.get_pixel(15, y + 10), SCROLL_COLOR);
+    assert_ne!(image.get_pixel(x + 1, y + 10), SCROLL_COLOR);
+}
+
+#[test]
+fn oam_previews_visible_sprites() {
+    let mut ppu = test_ppu();
+    let mut sprites = [HIDDEN_SPRITE_Y; 256];
+    sprites[4..8].copy_from_slice(&[20, SOLID_TILE, 0, 30]);
+    ppu.write_to_oam_dma(&sprites);
+
+    let image = oam(&ppu);
+    let center = |sprite: usize| {
+        (
+            (sprite % OAM_COLUMNS) * OAM_CELL_WIDTH + OAM_CELL_WIDTH / 2,
+            (sprite / OAM_COLUMNS) * OAM_CELL_HEIGHT + OAM_CELL_HEIGHT / 2,
+        )
+    };
+
+    let (x, y) = center(0);
+    assert_eq!(image.get_pixel(x, y), CELL_COLOR);
+    let (x, y) = center(1);
+    assert_eq!(image.get_pixel(x, y), color(&ppu, SPRITE_COLOR));
+}
+
+#[test]
+fn oam_cells_map_back_to_sprites() {
+    assert_eq!(oam_sprite_at(0, 0), Some(0));
+    assert_eq!(oam_sprite_at(OAM_CELL_WIDTH + 1, 0), Some(1));
+    assert_eq!(oam_sprite_at(0, OAM_CELL_HEIGHT), Some(OAM_COLUMNS as u8));
+    assert_eq!(oam_sprite_at(OAM_WIDTH - 1, OAM_HEIGHT - 1), Some(63));
+    assert_eq!(oam_sprite_at(OAM_WIDTH, 0), None);
+    assert_eq!(oam_sprite_at(0, OAM_HEIGHT), None);
+}
+
+#[test]
+fn palettes_show_every_entry() {
+    let ppu = test_ppu();
+
+    let image = palettes(&ppu);
+    assert_eq!(image.get_pixel(0, 0), color(&ppu, BACKDROP));
+    assert_eq!(image.get_pixel(SWATCH_SIZE, 0), color(&ppu, BG_COLOR));
+    assert_eq!(
+        image.get_pixel(SWATCH_SIZE, SWATCH_SIZE),
+        color(&ppu, SPRITE_COLOR)
+    );
+}
+
+#[test]
+fn events_are_marked_where_they_landed() {
+    let mut ppu = test_ppu();
+    ppu.enable_event_log();
+    ppu.log_write(0x2001, 0x1E);
+    ppu.log_write(0x8000, 0x01);
+    ppu.event_log.as_mut().unwrap().end_frame();
+
+    let image = events(&ppu);
+    let event = ppu.get_event_log().unwrap().get_events()[0];
+    assert_eq!(
+        image.get_pixel(event.dot + 1, event.scanline as usize + 1),
+        MAPPER_COLOR
+    );
+    assert_eq!(describe_event(&event), "0:0 PPUMASK $2001=$1E");
+}
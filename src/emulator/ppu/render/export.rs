@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::debug::{self, CHR_BANK_BYTES, COLORS_PER_PALETTE};
+use super::frame::Frame;
+use super::framing::Overscan;
+use super::palette::Palette;
+use super::png;
+use super::post::{Image, PostProcess};
+use crate::emulator::ppu::Ppu;
+
+const SCREENSHOT_PREFIX: &str = "screenshot";
+const FRAME_PREFIX: &str = "frame";
+
+// black to white, the way most tile editors show raw CHR
+pub const DEFAULT_CHR_PALETTE: [u8; COLORS_PER_PALETTE] = [0x0F, 0x00, 0x10, 0x30];
+
+pub fn chr_colors(
+    palette: &Palette,
+    indices: [u8; COLORS_PER_PALETTE],
+) -> [u32; COLORS_PER_PALETTE] {
+    indices.map(|index| debug::pack(palette.color(index, 0)))
+}
+
+// a trailing partial bank is padded with empty tiles
+pub fn chr_banks(chr: &[u8], colors: &[u32; COLORS_PER_PALETTE]) -> Vec<Image> {
+    (0..chr.len().div_ceil(CHR_BANK_BYTES))
+        .map(|bank| debug::chr_bank(chr, bank, colors))
+        .collect()
+}
+
+pub fn save_png(path: &str, image: &Image) -> Result<(), String> {
+    let data = png::encode_rgb(image.width, image.height, &image.data);
+    fs::write(path, data).map_err(|err| format!("{path}: {err}"))
+}
+
+// writes PREFIX_bankNN.png for every 4KB bank and returns the paths
+pub fn export_chr(
+    chr: &[u8],
+    colors: &[u32; COLORS_PER_PALETTE],
+    prefix: &str,
+) -> Result<Vec<String>, String> {
+    if chr.is_empty() {
+        return Err("the ROM has no CHR ROM, its tiles live in CHR RAM".to_string());
+    }
+
+    chr_banks(chr, colors)
+        .iter()
+        .enumerate()
+        .map(|(bank, image)| {
+            let path = format!("{prefix}_bank{bank:02}.png");
+            save_png(&path, image).map(|_| path)
+        })
+        .collect()
+}
+
+// the full 512x480 map with the palettes the game has loaded right now
+pub fn export_nametables(ppu: &Ppu, path: &str) -> Result<(), String> {
+    save_png(path, &debug::nametable_map(ppu))
+}
+
+// the overscan crop, then the scaler and effects, on the plain RGB frame; the NTSC filter
+// only runs for the window, so captures are what the window shows without it
+#[derive(Clone, Default)]
+pub struct Capture {
+    pub overscan: Overscan,
+    pub post_process: PostProcess,
+}
+
+impl Capture {
+    pub fn size(&self) -> (usize, usize) {
+        let (width, height) = self.overscan.visible_size();
+        let factor = self.post_process.scaler.factor();
+        (width * factor, height * factor)
+    }
+
+    pub fn image(&self, frame: &Frame) -> Image {
+        self.post_process
+            .apply(&self.overscan.crop(&Image::from_frame(frame)))
+    }
+}
+
+pub fn save_screen(ppu: &Ppu, path: &str, capture: &Capture) -> Result<(), String> {
+    save_png(path, &capture.image(&ppu.screen))
+}
+
+// SCREENSHOT_SECONDS_MILLIS_FRAME.png, so several shots in one second don't collide
+pub fn save_screenshot(ppu: &Ppu, dir: &str, capture: &Capture) -> Result<String, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = format!(
+        "{SCREENSHOT_PREFIX}_{}_{:03}_{}.png",
+        now.as_secs(),
+        now.subsec_millis(),
+        ppu.get_frame_count()
+    );
+    let path = Path::new(dir).join(name).to_string_lossy().into_owned();
+    save_screen(ppu, &path, capture).map(|_| path)
+}
+
+// writes every Nth frame as DIR/frame_NNNNNN.png
+pub struct FrameDump {
+    dir: String,
+    interval: usize,
+    capture: Capture,
+}
+
+impl FrameDump {
+    pub fn new(dir: &str, interval: usize, capture: Capture) -> Result<Self, String> {
+        if interval == 0 {
+            return Err("frame dump interval must be at least 1".to_string());
+        }
+        fs::create_dir_all(dir).map_err(|err| format!("{dir}: {err}"))?;
+
+        Ok(FrameDump {
+            dir: dir.to_string(),
+            interval,
+            capture,
+        })
+    }
+
+    pub fn capture(&self, ppu: &Ppu) -> Result<Option<String>, String> {
+        let frame = ppu.get_frame_count();
+        if !frame.is_multiple_of(self.interval) {
+            return Ok(None);
+        }
+
+        let path = Path::new(&self.dir)
+            .join(format!("{FRAME_PREFIX}_{frame:06}.png"))
+            .to_string_lossy()
+            .into_owned();
+        save_screen(ppu, &path, &self.capture).map(|_| Some(path))
+    }
+}
+
+#[cfg(test)]
+mod test;
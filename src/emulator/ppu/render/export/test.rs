@@ -0,0 +1,211 @@
+use super::*;
+use crate::emulator::ppu::nametables::Nametables;
+use crate::emulator::ppu::render::framing::Overscan;
+use crate::emulator::ppu::render::post::{Effect, Scaler};
+use crate::emulator::rom::Mirroring;
+use crate::emulator::test_helpers::temp_dir;
+
+const TILE_BYTES: usize = 16;
+const TILE_SIZE: usize = 8;
+const COLORS: [u32; COLORS_PER_PALETTE] = [0x000000, 0x555555, 0xAAAAAA, 0xFFFFFF];
+
+#[test]
+fn chr_banks_split_every_4kb() {
+    let mut chr = vec![0; 3 * CHR_BANK_BYTES];
+    // tile 1 of the second bank uses colour 3 on its first row
+    chr[CHR_BANK_BYTES + TILE_BYTES] = 0xFF;
+    chr[CHR_BANK_BYTES + TILE_BYTES + TILE_SIZE] = 0xFF;
+
+    let banks = chr_banks(&chr, &COLORS);
+    assert_eq!(banks.len(), 3);
+    assert_eq!(banks[0].get_pixel(TILE_SIZE, 0), COLORS[0]);
+    assert_eq!(banks[1].get_pixel(TILE_SIZE, 0), COLORS[3]);
+    assert_eq!(banks[1].get_pixel(TILE_SIZE, 1), COLORS[0]);
+}
+
+#[test]
+fn partial_bank_is_padded() {
+    let chr = vec![0xFF; CHR_BANK_BYTES + TILE_BYTES];
+
+    let banks = chr_banks(&chr, &COLORS);
+    assert_eq!(banks.len(), 2);
+    assert_eq!(banks[1].get_pixel(0, 0), COLORS[3]);
+    assert_eq!(banks[1].get_pixel(TILE_SIZE, 0), COLORS[0]);
+}
+
+#[test]
+fn chr_colors_come_from_the_palette() {
+    let palette = Palette::default();
+    let colors = chr_colors(&palette, DEFAULT_CHR_PALETTE);
+    assert_eq!(colors[0], debug::pack(palette.color(0x0F, 0)));
+    assert_eq!(colors[3], debug::pack(palette.color(0x30, 0)));
+}
+
+#[test]
+fn export_chr_writes_a_png_per_bank() {
+    let prefix = format!("{}/game", temp_dir("export/chr"));
+    let chr = vec![0; 2 * CHR_BANK_BYTES];
+
+    let paths = export_chr(&chr, &COLORS, &prefix).unwrap();
+    assert_eq!(
+        paths,
+        [
+            format!("{prefix}_bank00.png"),
+            format!("{prefix}_bank01.png")
+        ]
+    );
+
+    let expected = png::encode_rgb(
+        debug::CHR_BANK_SIZE,
+        debug::CHR_BANK_SIZE,
+        &chr_banks(&chr, &COLORS)[1].data,
+    );
+    assert_eq!(fs::read(&paths[1]).unwrap(), expected);
+}
+
+#[test]
+fn export_chr_needs_chr_rom() {
+    assert!(export_chr(&[], &COLORS, "unused").is_err());
+}
+
+#[test]
+fn export_nametables_writes_the_full_map() {
+    let path = format!("{}/nametables.png", temp_dir("export/nametables"));
+    let ppu = Ppu::new(
+        vec![0; 2 * CHR_BANK_BYTES],
+        Nametables::new(Mirroring::Horizontal, Vec::new()),
+    );
+
+    export_nametables(&ppu, &path).unwrap();
+    let map = debug::nametable_map(&ppu);
+    assert_eq!(
+        (map.width, map.height),
+        (debug::NAMETABLES_WIDTH, debug::NAMETABLES_HEIGHT)
+    );
+    assert_eq!(
+        fs::read(&path).unwrap(),
+        png::encode_rgb(map.width, map.height, &map.data)
+    );
+}
+
+fn screen_ppu() -> Ppu {
+    let mut ppu = Ppu::new(
+        vec![0; 2 * CHR_BANK_BYTES],
+        Nametables::new(Mirroring::Horizontal, Vec::new()),
+    );
+    ppu.screen.set_pixel(3, 4, (0x12, 0x34, 0x56));
+    ppu
+}
+
+fn screen_png(ppu: &Ppu) -> Vec<u8> {
+    png::encode_rgb(Frame::WIDTH, Frame::HIGHT, &ppu.screen.data)
+}
+
+#[test]
+fn screenshots_are_timestamped_pngs_of_the_screen() {
+    let dir = temp_dir("export/screenshots");
+    let mut ppu = screen_ppu();
+    ppu.frame_count = 42;
+
+    let path = save_screenshot(&ppu, &dir, &Capture::default()).unwrap();
+    let name = Path::new(&path).file_name().unwrap().to_string_lossy();
+    assert!(name.starts_with("screenshot_"));
+    assert!(name.ends_with("_42.png"));
+    assert_eq!(fs::read(&path).unwrap(), screen_png(&ppu));
+}
+
+#[test]
+fn frame_dump_writes_every_nth_frame() {
+    let dir = temp_dir("export/frames");
+    let dump = FrameDump::new(&dir, 3, Capture::default()).unwrap();
+    let mut ppu = screen_ppu();
+
+    ppu.frame_count = 4;
+    assert_eq!(dump.capture(&ppu).unwrap(), None);
+
+    ppu.frame_count = 6;
+    let path = dump.capture(&ppu).unwrap().unwrap();
+    assert_eq!(path, format!("{dir}/frame_000006.png"));
+    assert_eq!(fs::read(&path).unwrap(), screen_png(&ppu));
+}
+
+#[test]
+fn frame_dump_needs_an_interval() {
+    assert!(FrameDump::new(&temp_dir("export/no_interval"), 0, Capture::default()).is_err());
+}
+
+fn scaled_capture() -> Capture {
+    Capture {
+        overscan: Overscan::default(),
+        post_process: PostProcess {
+            scaler: Scaler::Nearest(2),
+            effects: vec![Effect::Scanlines(0.5)],
+        },
+    }
+}
+
+#[test]
+fn capture_matches_the_post_processed_window() {
+    let ppu = screen_ppu();
+    let capture = scaled_capture();
+
+    let image = capture.image(&ppu.screen);
+    assert_eq!((image.width, image.height), capture.size());
+    assert_eq!(
+        (image.width, image.height),
+        (2 * Frame::WIDTH, 2 * Frame::HIGHT)
+    );
+    assert_eq!(image.get_pixel(6, 8), 0x123456);
+    assert_ne!(image.get_pixel(6, 9), 0x123456);
+}
+
+#[test]
+fn screenshots_and_frame_dumps_use_the_capture() {
+    let dir = temp_dir("export/scaled");
+    let ppu = screen_ppu();
+    let capture = scaled_capture();
+    let image = capture.image(&ppu.screen);
+    let expected = png::encode_rgb(image.width, image.height, &image.data);
+
+    let path = save_screenshot(&ppu, &dir, &capture).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), expected);
+
+    let dump = FrameDump::new(&dir, 1, capture).unwrap();
+    let path = dump.capture(&ppu).unwrap().unwrap();
+    assert_eq!(fs::read(&path).unwrap(), expected);
+}
+
+#[test]
+fn capture_crops_the_overscan_before_scaling() {
+    let ppu = screen_ppu();
+    let capture = Capture {
+        overscan: Overscan {
+            top: 4,
+            bottom: 8,
+            left: 3,
+            right: 0,
+        },
+        ..scaled_capture()
+    };
+
+    let image = capture.image(&ppu.screen);
+    assert_eq!((image.width, image.height), capture.size());
+    assert_eq!((image.width, image.height), (2 * 253, 2 * 228));
+    assert_eq!(image.get_pixel(0, 0), 0x123456);
+
+    let path = save_screenshot(&ppu, &temp_dir("export/cropped"), &capture).unwrap();
+    assert_eq!(
+        fs::read(&path).unwrap(),
+        png::encode_rgb(image.width, image.height, &image.data)
+    );
+}
+
+#[test]
+fn frame_dump_reports_write_errors() {
+    let dir = temp_dir("export/removed");
+    let dump = FrameDump::new(&dir, 1, Capture::default()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let err = dump.capture(&screen_ppu()).unwrap_err();
+    assert!(err.starts_with(&dir), "{err}");
+}
@@ -3,8 +3,8 @@ pub struct Frame {
 }
 
 impl Frame {
-    const WIDTH: usize = 256;
-    const HIGHT: usize = 240;
+    pub const WIDTH: usize = 256;
+    pub const HIGHT: usize = 240;
     const NUM_OF_PIXELS: usize = Frame::WIDTH * Frame::HIGHT;
     const PIXEL_SIZE: usize = 3;
 
@@ -25,3 +25,26 @@ impl Frame {
         }
     }
 }
+
+// 6 bit palette colour in the low bits, the 3 PPUMASK emphasis bits above it
+pub struct IndexFrame {
+    pub data: Vec<u16>,
+}
+
+impl IndexFrame {
+    pub const EMPHASIS_SHIFT: u16 = 6;
+    pub const COLOR_MASK: u16 = 0b11_1111;
+
+    pub fn new() -> Self {
+        IndexFrame {
+            data: vec![0; Frame::NUM_OF_PIXELS],
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u8, emphasis: u8) {
+        if let Some(pixel) = self.data.get_mut(y * Frame::WIDTH + x) {
+            *pixel =
+                (color as u16 & Self::COLOR_MASK) | ((emphasis as u16) << Self::EMPHASIS_SHIFT);
+        }
+    }
+}
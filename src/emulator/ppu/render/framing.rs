@@ -0,0 +1,84 @@
+use super::frame::Frame;
+use super::post::Image;
+
+const RGB_SIZE: usize = 3;
+const NTSC_SAFE_LINES: usize = 8;
+const PIXEL_ASPECT_WIDTH: f32 = 8.0;
+const PIXEL_ASPECT_HEIGHT: f32 = 7.0;
+const DISPLAY_ASPECT_WIDTH: f32 = 4.0;
+const DISPLAY_ASPECT_HEIGHT: f32 = 3.0;
+
+// edges are counted in NES pixels, so they line up on filtered frames wider than 256
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Overscan {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum AspectRatio {
+    #[default]
+    Square,
+    // NTSC pixels are slightly wider than they are tall
+    Pixel8To7,
+    Display4To3,
+}
+
+impl Overscan {
+    pub fn ntsc_safe_area() -> Self {
+        Overscan {
+            top: NTSC_SAFE_LINES,
+            bottom: NTSC_SAFE_LINES,
+            left: 0,
+            right: 0,
+        }
+    }
+
+    // size of a cropped frame in NES pixels
+    pub fn visible_size(&self) -> (usize, usize) {
+        (
+            Frame::WIDTH.saturating_sub(self.left + self.right),
+            Frame::HIGHT.saturating_sub(self.top + self.bottom),
+        )
+    }
+
+    pub fn crop(&self, image: &Image) -> Image {
+        let to_columns = |pixels: usize| pixels * image.width / Frame::WIDTH;
+        let left = to_columns(self.left).min(image.width);
+        let right = to_columns(self.right).min(image.width - left);
+        let top = self.top.min(image.height);
+        let bottom = self.bottom.min(image.height - top);
+
+        let width = image.width - left - right;
+        let height = image.height - top - bottom;
+        let mut cropped = Image::new(width, height);
+
+        for y in 0..height {
+            let src = ((y + top) * image.width + left) * RGB_SIZE;
+            let dst = y * width * RGB_SIZE;
+            cropped.data[dst..dst + width * RGB_SIZE]
+                .copy_from_slice(&image.data[src..src + width * RGB_SIZE]);
+        }
+        cropped
+    }
+}
+
+impl AspectRatio {
+    // presentation size of a width x height NES pixel picture
+    pub fn display_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let display_width = match self {
+            AspectRatio::Square => width as f32,
+            AspectRatio::Pixel8To7 => width as f32 * PIXEL_ASPECT_WIDTH / PIXEL_ASPECT_HEIGHT,
+            AspectRatio::Display4To3 => {
+                height as f32 * DISPLAY_ASPECT_WIDTH / DISPLAY_ASPECT_HEIGHT
+            }
+        };
+
+        (display_width.round() as usize, height)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,68 @@
+use super::*;
+
+fn numbered_frame(width: usize) -> Image {
+    let mut image = Image::new(width, Frame::HIGHT);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            image.set_pixel(x, y, ((x as u32) << 8) | y as u32);
+        }
+    }
+    image
+}
+
+#[test]
+fn crops_every_edge() {
+    let overscan = Overscan {
+        top: 8,
+        bottom: 16,
+        left: 4,
+        right: 2,
+    };
+    let cropped = overscan.crop(&numbered_frame(Frame::WIDTH));
+
+    assert_eq!((cropped.width, cropped.height), overscan.visible_size());
+    assert_eq!((cropped.width, cropped.height), (250, 216));
+    assert_eq!(cropped.get_pixel(0, 0), (4 << 8) | 8);
+    assert_eq!(cropped.get_pixel(249, 215), (253 << 8) | 223);
+}
+
+#[test]
+fn columns_scale_with_wider_frames() {
+    let overscan = Overscan {
+        left: 8,
+        right: 8,
+        ..Overscan::ntsc_safe_area()
+    };
+    let cropped = overscan.crop(&numbered_frame(Frame::WIDTH * 2));
+
+    assert_eq!((cropped.width, cropped.height), (480, 224));
+    assert_eq!(cropped.get_pixel(0, 0), (16 << 8) | 8);
+}
+
+#[test]
+fn oversized_overscan_leaves_an_empty_image() {
+    let overscan = Overscan {
+        top: 200,
+        bottom: 200,
+        left: 300,
+        right: 0,
+    };
+    let cropped = overscan.crop(&numbered_frame(Frame::WIDTH));
+
+    assert_eq!((cropped.width, cropped.height), (0, 0));
+}
+
+#[test]
+fn aspect_ratios() {
+    let (width, height) = Overscan::ntsc_safe_area().visible_size();
+
+    assert_eq!(AspectRatio::Square.display_size(width, height), (256, 224));
+    assert_eq!(
+        AspectRatio::Pixel8To7.display_size(width, height),
+        (293, 224)
+    );
+    assert_eq!(
+        AspectRatio::Display4To3.display_size(width, height),
+        (299, 224)
+    );
+}
@@ -0,0 +1,195 @@
+use super::frame::{Frame, IndexFrame};
+use super::palette::{
+    NUM_OF_EMPHASIS, NtscPaletteSettings, PHASES, adjust_level, hue_shift, phase_angle,
+    signal_level, yiq_to_rgb,
+};
+use super::pallete_table::NUM_OF_PALLETES;
+
+const NUM_OF_COLORS: usize = NUM_OF_PALLETES * NUM_OF_EMPHASIS;
+const RGB_SIZE: usize = 3;
+
+// every PPU dot is 8 master clocks, a subcarrier cycle is 12
+const SAMPLES_PER_PIXEL: usize = 8;
+const LINE_SAMPLES: usize = Frame::WIDTH * SAMPLES_PER_PIXEL;
+// 341 dots * 8 samples leaves the next line 4 phases further along
+const PHASE_SHIFT_PER_LINE: usize = 4;
+
+const LUMA_WINDOW: usize = PHASES;
+const SOFT_LUMA_WINDOW: usize = 2 * PHASES;
+const FRINGE_WINDOW: usize = PHASES / 3;
+const CHROMA_WINDOW: usize = PHASES;
+
+#[derive(Clone, Copy)]
+pub struct NtscFilterSettings {
+    pub palette: NtscPaletteSettings,
+    // -1 blurs, 1 sharpens the luma
+    pub sharpness: f32,
+    // 0-1, chroma that leaks into luma around colour edges
+    pub fringing: f32,
+    // 0-1, luma that leaks into chroma around brightness edges
+    pub artifacts: f32,
+    pub dot_crawl: bool,
+}
+
+impl NtscFilterSettings {
+    pub fn composite() -> Self {
+        NtscFilterSettings {
+            palette: NtscPaletteSettings::default(),
+            sharpness: 0.0,
+            fringing: 1.0,
+            artifacts: 1.0,
+            dot_crawl: true,
+        }
+    }
+
+    pub fn svideo() -> Self {
+        NtscFilterSettings {
+            sharpness: 0.2,
+            fringing: 0.0,
+            ..Self::composite()
+        }
+    }
+
+    pub fn rgb() -> Self {
+        NtscFilterSettings {
+            sharpness: 0.2,
+            fringing: 0.0,
+            artifacts: 0.0,
+            dot_crawl: false,
+            ..Self::composite()
+        }
+    }
+}
+
+pub struct NtscFilter {
+    settings: NtscFilterSettings,
+    levels: Vec<[f32; PHASES]>,
+    clean_chroma: Vec<(f32, f32)>,
+    carrier: [(f32, f32); PHASES],
+    luma_sums: Vec<f32>,
+    i_sums: Vec<f32>,
+    q_sums: Vec<f32>,
+    pub data: Vec<u8>,
+}
+
+impl NtscFilter {
+    pub const WIDTH: usize = 602;
+
+    pub fn new(settings: NtscFilterSettings) -> Self {
+        let hue_shift = hue_shift(&settings.palette);
+        let carrier: [(f32, f32); PHASES] = std::array::from_fn(|phase| {
+            let angle = phase_angle(phase as f32 + hue_shift);
+            (angle.cos(), angle.sin())
+        });
+
+        let levels: Vec<[f32; PHASES]> = (0..NUM_OF_COLORS)
+            .map(|color| {
+                let (index, emphasis) = split_color(color as u16);
+                std::array::from_fn(|phase| {
+                    adjust_level(signal_level(index, emphasis, phase), &settings.palette)
+                })
+            })
+            .collect();
+
+        // chroma of a flat field of the colour, what a perfect Y/C separation would recover
+        let clean_chroma = levels
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .zip(carrier.iter())
+                    .fold((0.0, 0.0), |(i, q), (l, (c, s))| {
+                        (i + l * c / PHASES as f32, q + l * s / PHASES as f32)
+                    })
+            })
+            .collect();
+
+        NtscFilter {
+            settings,
+            levels,
+            clean_chroma,
+            carrier,
+            luma_sums: vec![0.0; LINE_SAMPLES + 1],
+            i_sums: vec![0.0; LINE_SAMPLES + 1],
+            q_sums: vec![0.0; LINE_SAMPLES + 1],
+            data: vec![0; Self::WIDTH * Frame::HIGHT * RGB_SIZE],
+        }
+    }
+
+    pub fn render(&mut self, indices: &IndexFrame, frame_count: usize) {
+        let frame_phase = if self.settings.dot_crawl {
+            (frame_count * PHASE_SHIFT_PER_LINE) % PHASES
+        } else {
+            0
+        };
+
+        for y in 0..Frame::HIGHT {
+            let line = &indices.data[y * Frame::WIDTH..(y + 1) * Frame::WIDTH];
+            let line_phase = (frame_phase + y * PHASE_SHIFT_PER_LINE) % PHASES;
+            self.modulate_line(line, line_phase);
+
+            for x in 0..Self::WIDTH {
+                let rgb = self.decode_pixel(line, x);
+                let base = (y * Self::WIDTH + x) * RGB_SIZE;
+                self.data[base] = rgb.0;
+                self.data[base + 1] = rgb.1;
+                self.data[base + 2] = rgb.2;
+            }
+        }
+    }
+
+    // prefix sums of the composite signal and of its products with the subcarrier
+    fn modulate_line(&mut self, line: &[u16], line_phase: usize) {
+        for sample in 0..LINE_SAMPLES {
+            let color = line[sample / SAMPLES_PER_PIXEL] as usize % NUM_OF_COLORS;
+            let phase = (line_phase + sample) % PHASES;
+            let level = self.levels[color][phase];
+            let (cos, sin) = self.carrier[phase];
+
+            self.luma_sums[sample + 1] = self.luma_sums[sample] + level;
+            self.i_sums[sample + 1] = self.i_sums[sample] + level * cos;
+            self.q_sums[sample + 1] = self.q_sums[sample] + level * sin;
+        }
+    }
+
+    fn decode_pixel(&self, line: &[u16], x: usize) -> (u8, u8, u8) {
+        let center = (x * 2 + 1) * LINE_SAMPLES / (Self::WIDTH * 2);
+
+        let luma = window_mean(&self.luma_sums, center, LUMA_WINDOW);
+        let soft_luma = window_mean(&self.luma_sums, center, SOFT_LUMA_WINDOW);
+        let fringe_luma = window_mean(&self.luma_sums, center, FRINGE_WINDOW);
+        let y = luma
+            + self.settings.sharpness * (luma - soft_luma)
+            + self.settings.fringing * (fringe_luma - luma);
+
+        let color = line[center / SAMPLES_PER_PIXEL] as usize % NUM_OF_COLORS;
+        let (clean_i, clean_q) = self.clean_chroma[color];
+        let i = window_mean(&self.i_sums, center, CHROMA_WINDOW);
+        let q = window_mean(&self.q_sums, center, CHROMA_WINDOW);
+
+        yiq_to_rgb(
+            y,
+            clean_i + self.settings.artifacts * (i - clean_i),
+            clean_q + self.settings.artifacts * (q - clean_q),
+            &self.settings.palette,
+        )
+    }
+}
+
+fn split_color(color: u16) -> (u8, u8) {
+    (
+        (color & IndexFrame::COLOR_MASK) as u8,
+        (color >> IndexFrame::EMPHASIS_SHIFT) as u8,
+    )
+}
+
+fn window_mean(sums: &[f32], center: usize, width: usize) -> f32 {
+    let start = center.saturating_sub(width / 2);
+    let end = (start + width).min(sums.len() - 1);
+    let start = end.saturating_sub(width);
+
+    (sums[end] - sums[start]) / (end - start) as f32
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,103 @@
+use super::super::palette::Palette;
+use super::*;
+
+const RED: u16 = 0x16;
+const WHITE: u16 = 0x30;
+const BLACK: u16 = 0x0F;
+const TOLERANCE: i16 = 2;
+
+fn flat_frame(color: u16) -> IndexFrame {
+    let mut frame = IndexFrame::new();
+    frame.data.fill(color);
+    frame
+}
+
+fn pixel(filter: &NtscFilter, x: usize, y: usize) -> (u8, u8, u8) {
+    let base = (y * NtscFilter::WIDTH + x) * RGB_SIZE;
+    (
+        filter.data[base],
+        filter.data[base + 1],
+        filter.data[base + 2],
+    )
+}
+
+fn close(a: (u8, u8, u8), b: (u8, u8, u8)) -> bool {
+    let diff = |x: u8, y: u8| (x as i16 - y as i16).abs() <= TOLERANCE;
+    diff(a.0, b.0) && diff(a.1, b.1) && diff(a.2, b.2)
+}
+
+#[test]
+fn output_is_602_pixels_wide() {
+    let mut filter = NtscFilter::new(NtscFilterSettings::composite());
+    filter.render(&flat_frame(RED), 0);
+
+    assert_eq!(
+        filter.data.len(),
+        NtscFilter::WIDTH * Frame::HIGHT * RGB_SIZE
+    );
+}
+
+#[test]
+fn flat_fields_decode_to_the_palette_colour() {
+    let settings = NtscFilterSettings::svideo();
+    let palette = Palette::generate_ntsc(&settings.palette);
+    let mut filter = NtscFilter::new(NtscFilterSettings {
+        sharpness: 0.0,
+        ..settings
+    });
+
+    for color in [
+        RED,
+        WHITE,
+        BLACK,
+        RED | (0b101 << IndexFrame::EMPHASIS_SHIFT),
+    ] {
+        filter.render(&flat_frame(color), 0);
+        let (index, emphasis) = split_color(color);
+        assert!(
+            close(pixel(&filter, 300, 120), palette.color(index, emphasis)),
+            "colour {color:#X}"
+        );
+    }
+}
+
+#[test]
+fn composite_edges_show_artifacts_that_rgb_does_not() {
+    let mut frame = flat_frame(BLACK);
+    for (i, pixel) in frame.data.iter_mut().enumerate() {
+        if (i % Frame::WIDTH) % 2 == 0 {
+            *pixel = WHITE;
+        }
+    }
+
+    let mut composite = NtscFilter::new(NtscFilterSettings::composite());
+    composite.render(&frame, 0);
+    let mut rgb = NtscFilter::new(NtscFilterSettings::rgb());
+    rgb.render(&frame, 0);
+
+    let is_grey = |(r, g, b): (u8, u8, u8)| {
+        r.abs_diff(g) <= TOLERANCE as u8 && g.abs_diff(b) <= TOLERANCE as u8
+    };
+    assert!(is_grey(pixel(&rgb, 300, 120)));
+    assert!(!is_grey(pixel(&composite, 300, 120)));
+}
+
+#[test]
+fn dot_crawl_moves_between_frames() {
+    let frame = flat_frame(RED);
+
+    let mut filter = NtscFilter::new(NtscFilterSettings::composite());
+    filter.render(&frame, 0);
+    let first = filter.data.clone();
+    filter.render(&frame, 1);
+    assert_ne!(first, filter.data);
+
+    let mut filter = NtscFilter::new(NtscFilterSettings {
+        dot_crawl: false,
+        ..NtscFilterSettings::composite()
+    });
+    filter.render(&frame, 0);
+    let first = filter.data.clone();
+    filter.render(&frame, 1);
+    assert_eq!(first, filter.data);
+}
@@ -0,0 +1,183 @@
+use std::f32::consts::PI;
+use std::fs;
+
+use super::pallete_table::{NUM_OF_PALLETES, SYSTEM_PALLETE, emphasize};
+
+pub const NUM_OF_EMPHASIS: usize = 8;
+pub const PHASES: usize = 12;
+
+const RGB_SIZE: usize = 3;
+const BASIC_PAL_SIZE: usize = NUM_OF_PALLETES * RGB_SIZE;
+const EMPHASIS_PAL_SIZE: usize = BASIC_PAL_SIZE * NUM_OF_EMPHASIS;
+
+// composite signal levels of the 2C02 in volts, low then high for the 4 luma rows
+const SIGNAL_LEVELS: [f32; 8] = [0.350, 0.518, 0.962, 1.550, 1.094, 1.506, 1.962, 1.962];
+const BLACK_LEVEL: f32 = 0.518;
+const WHITE_LEVEL: f32 = 1.962;
+const SIGNAL_EMPHASIS_ATTENUATION: f32 = 0.746;
+const DEGREES_PER_PHASE: f32 = 30.0;
+const GAMMA_REFERENCE: f32 = 2.2;
+const BLACK_HUE: u8 = 0x0D;
+const FORCED_BLACK_HUE: u8 = 0x0E;
+const HUE_MASK: u8 = 0x0F;
+const LUMA_SHIFT: u8 = 4;
+const LUMA_MASK: u8 = 0b11;
+
+#[derive(Clone)]
+pub struct Palette {
+    colors: Vec<(u8, u8, u8)>,
+}
+
+#[derive(Clone, Copy)]
+pub struct NtscPaletteSettings {
+    pub hue: f32,
+    pub saturation: f32,
+    pub contrast: f32,
+    pub brightness: f32,
+    pub gamma: f32,
+}
+
+impl Default for NtscPaletteSettings {
+    fn default() -> Self {
+        NtscPaletteSettings {
+            hue: 0.0,
+            saturation: 1.0,
+            contrast: 1.0,
+            brightness: 1.0,
+            gamma: 1.8,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            colors: SYSTEM_PALLETE.to_vec(),
+        }
+    }
+}
+
+impl Palette {
+    // .pal files hold 64 colours, or 8 blocks of 64 with every emphasis combination
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if (data.len() != BASIC_PAL_SIZE) && (data.len() != EMPHASIS_PAL_SIZE) {
+            return Err(format!(
+                "palette size {} is neither {BASIC_PAL_SIZE} nor {EMPHASIS_PAL_SIZE} bytes",
+                data.len()
+            ));
+        }
+
+        Ok(Palette {
+            colors: data
+                .chunks_exact(RGB_SIZE)
+                .map(|rgb| (rgb[0], rgb[1], rgb[2]))
+                .collect(),
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|err| format!("{path}: {err}"))?;
+        Self::from_bytes(&data)
+    }
+
+    // decodes the composite waveform the PPU would output for every colour and emphasis
+    pub fn generate_ntsc(settings: &NtscPaletteSettings) -> Self {
+        let colors = (0..NUM_OF_EMPHASIS)
+            .flat_map(|emphasis| {
+                (0..NUM_OF_PALLETES)
+                    .map(move |index| ntsc_color(index as u8, emphasis as u8, settings))
+            })
+            .collect();
+
+        Palette { colors }
+    }
+
+    pub fn has_emphasis(&self) -> bool {
+        self.colors.len() == NUM_OF_PALLETES * NUM_OF_EMPHASIS
+    }
+
+    pub fn color(&self, index: u8, emphasis: u8) -> (u8, u8, u8) {
+        let index = index as usize % NUM_OF_PALLETES;
+
+        if self.has_emphasis() {
+            self.colors[emphasis as usize * NUM_OF_PALLETES + index]
+        } else {
+            emphasize(self.colors[index], emphasis)
+        }
+    }
+}
+
+fn ntsc_color(index: u8, emphasis: u8, settings: &NtscPaletteSettings) -> (u8, u8, u8) {
+    let hue_shift = hue_shift(settings);
+
+    let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+    for phase in 0..PHASES {
+        let level = adjust_level(signal_level(index, emphasis, phase), settings) / PHASES as f32;
+        let angle = phase_angle(phase as f32 + hue_shift);
+        y += level;
+        i += level * angle.cos();
+        q += level * angle.sin();
+    }
+
+    yiq_to_rgb(y, i, q, settings)
+}
+
+// normalized composite level of one of the 12 subcarrier phases, 0 is black and 1 white
+pub(super) fn signal_level(index: u8, emphasis: u8, phase: usize) -> f32 {
+    let hue = index & HUE_MASK;
+    let luma = if hue < FORCED_BLACK_HUE {
+        (index >> LUMA_SHIFT) & LUMA_MASK
+    } else {
+        1
+    };
+
+    let low = SIGNAL_LEVELS[luma as usize + if hue == 0 { 4 } else { 0 }];
+    let high = SIGNAL_LEVELS[luma as usize + if hue < BLACK_HUE { 4 } else { 0 }];
+
+    // the colour generator is high for 6 of the 12 phases, shifted by the hue
+    let in_phase = |color: u8| (color as usize + phase + 8) % PHASES < 6;
+    let mut signal = if in_phase(hue) { high } else { low };
+
+    if ((emphasis & 0b001) != 0 && in_phase(0x0C))
+        || ((emphasis & 0b010) != 0 && in_phase(0x04))
+        || ((emphasis & 0b100) != 0 && in_phase(0x08))
+    {
+        signal *= SIGNAL_EMPHASIS_ATTENUATION;
+    }
+
+    (signal - BLACK_LEVEL) / (WHITE_LEVEL - BLACK_LEVEL)
+}
+
+pub(super) fn adjust_level(level: f32, settings: &NtscPaletteSettings) -> f32 {
+    ((level - 0.5) * settings.contrast + 0.5) * settings.brightness
+}
+
+pub(super) fn phase_angle(phase: f32) -> f32 {
+    PI / 6.0 * phase
+}
+
+pub(super) fn hue_shift(settings: &NtscPaletteSettings) -> f32 {
+    settings.hue / DEGREES_PER_PHASE
+}
+
+pub(super) fn yiq_to_rgb(y: f32, i: f32, q: f32, settings: &NtscPaletteSettings) -> (u8, u8, u8) {
+    let (i, q) = (i * settings.saturation, q * settings.saturation);
+
+    let to_channel = |value: f32| {
+        let corrected = if value <= 0.0 {
+            0.0
+        } else {
+            value.powf(GAMMA_REFERENCE / settings.gamma)
+        };
+        (corrected * 255.0).clamp(0.0, 255.0) as u8
+    };
+
+    (
+        to_channel(y + 0.946882 * i + 0.623557 * q),
+        to_channel(y - 0.274788 * i - 0.635691 * q),
+        to_channel(y - 1.108545 * i + 1.709007 * q),
+    )
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,54 @@
+use super::*;
+
+const RED: u8 = 0x16;
+const GREEN: u8 = 0x1A;
+const BLUE: u8 = 0x12;
+const WHITE: u8 = 0x30;
+const BLACK: u8 = 0x0F;
+
+#[test]
+fn loads_basic_and_emphasis_palettes() {
+    let basic: Vec<u8> = (0..BASIC_PAL_SIZE).map(|i| i as u8).collect();
+    let palette = Palette::from_bytes(&basic).unwrap();
+    assert!(!palette.has_emphasis());
+    assert_eq!(palette.color(1, 0), (3, 4, 5));
+
+    let mut full = vec![0; EMPHASIS_PAL_SIZE];
+    full[(3 * NUM_OF_PALLETES + 1) * RGB_SIZE] = 0xAB;
+    let palette = Palette::from_bytes(&full).unwrap();
+    assert!(palette.has_emphasis());
+    assert_eq!(palette.color(1, 3), (0xAB, 0, 0));
+}
+
+#[test]
+fn rejects_unknown_palette_sizes() {
+    assert!(Palette::from_bytes(&[0; BASIC_PAL_SIZE - 1]).is_err());
+    assert!(Palette::from_bytes(&[0; BASIC_PAL_SIZE * 2]).is_err());
+}
+
+#[test]
+fn ntsc_palette_has_expected_hues() {
+    let palette = Palette::generate_ntsc(&NtscPaletteSettings::default());
+    assert!(palette.has_emphasis());
+
+    let (r, g, b) = palette.color(RED, 0);
+    assert!(r > g && r > b);
+    let (r, g, b) = palette.color(GREEN, 0);
+    assert!(g > r && g > b);
+    let (r, g, b) = palette.color(BLUE, 0);
+    assert!(b > r && b > g);
+
+    assert_eq!(palette.color(BLACK, 0), (0, 0, 0));
+    let (r, g, b) = palette.color(WHITE, 0);
+    assert!(r > 0xF0 && g > 0xF0 && b > 0xF0);
+}
+
+#[test]
+fn ntsc_emphasis_darkens_the_other_channels() {
+    let palette = Palette::generate_ntsc(&NtscPaletteSettings::default());
+    let (r, g, b) = palette.color(WHITE, 0);
+    let (er, eg, eb) = palette.color(WHITE, 0b001);
+
+    assert!(eg < g && eb < b);
+    assert!(er >= eg && er <= r);
+}
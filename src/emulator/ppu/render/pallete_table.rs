@@ -1,4 +1,10 @@
 pub const NUM_OF_PALLETES: usize = 64;
+pub const GREYSCALE_MASK: u8 = 0x30;
+
+const EMPHASIS_ATTENUATION: f32 = 0.816328;
+const EMPHASIZE_RED: u8 = 0b001;
+const EMPHASIZE_GREEN: u8 = 0b010;
+const EMPHASIZE_BLUE: u8 = 0b100;
 
 #[rustfmt::skip]
 pub const SYSTEM_PALLETE: [(u8,u8,u8); NUM_OF_PALLETES] = [
@@ -16,3 +22,25 @@ pub const SYSTEM_PALLETE: [(u8,u8,u8); NUM_OF_PALLETES] = [
    (0xFF, 0xEF, 0xA6), (0xFF, 0xF7, 0x9C), (0xD7, 0xE8, 0x95), (0xA6, 0xED, 0xAF), (0xA2, 0xF2, 0xDA),
    (0x99, 0xFF, 0xFC), (0xDD, 0xDD, 0xDD), (0x11, 0x11, 0x11), (0x11, 0x11, 0x11)
 ];
+
+// every emphasized channel darkens the other two
+pub fn emphasize(rgb: (u8, u8, u8), emphasis: u8) -> (u8, u8, u8) {
+    if emphasis == 0 {
+        return rgb;
+    }
+
+    let attenuate = |channel: u8, own_bit: u8| {
+        if (emphasis & !own_bit) != 0 {
+            (channel as f32 * EMPHASIS_ATTENUATION) as u8
+        } else {
+            channel
+        }
+    };
+
+    let (r, g, b) = rgb;
+    (
+        attenuate(r, EMPHASIZE_RED),
+        attenuate(g, EMPHASIZE_GREEN),
+        attenuate(b, EMPHASIZE_BLUE),
+    )
+}
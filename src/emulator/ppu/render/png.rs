@@ -0,0 +1,96 @@
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const BIT_DEPTH: u8 = 8;
+const COLOR_TYPE_RGB: u8 = 2;
+const FILTER_NONE: u8 = 0;
+const RGB_PIXEL_SIZE: usize = 3;
+
+const ZLIB_HEADER: [u8; 2] = [0x78, 0x01];
+const MAX_STORED_BLOCK_SIZE: usize = 0xFFFF;
+const STORED_BLOCK: u8 = 0b00;
+const FINAL_BLOCK: u8 = 0b01;
+
+const CRC_POLYNOMIAL: u32 = 0xEDB8_8320;
+const ADLER_MODULO: u32 = 65521;
+
+pub fn encode_rgb(width: usize, height: usize, rgb: &[u8]) -> Vec<u8> {
+    assert_eq!(rgb.len(), width * height * RGB_PIXEL_SIZE);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[BIT_DEPTH, COLOR_TYPE_RGB, 0, 0, 0]);
+
+    let stride = width * RGB_PIXEL_SIZE;
+    let mut scanlines = Vec::with_capacity((stride + 1) * height);
+    for row in rgb.chunks(stride) {
+        scanlines.push(FILTER_NONE);
+        scanlines.extend_from_slice(row);
+    }
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let crc_start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[crc_start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = ZLIB_HEADER.to_vec();
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_SIZE).peekable();
+
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[STORED_BLOCK | FINAL_BLOCK, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = if blocks.peek().is_none() {
+            FINAL_BLOCK
+        } else {
+            0
+        };
+        let len = block.len() as u16;
+
+        out.push(STORED_BLOCK | last);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (CRC_POLYNOMIAL & mask);
+        }
+    }
+
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for byte in data {
+        a = (a + *byte as u32) % ADLER_MODULO;
+        b = (b + a) % ADLER_MODULO;
+    }
+
+    (b << 16) | a
+}
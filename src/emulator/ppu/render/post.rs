@@ -0,0 +1,457 @@
+use super::frame::Frame;
+
+const RGB_SIZE: usize = 3;
+
+// colour thresholds in YUV space, taken from hqx
+const Y_THRESHOLD: i32 = 48;
+const U_THRESHOLD: i32 = 7;
+const V_THRESHOLD: i32 = 6;
+
+// xBR weighs luma much more than chroma when measuring edges
+const XBR_Y_WEIGHT: i32 = 48;
+const XBR_U_WEIGHT: i32 = 7;
+const XBR_V_WEIGHT: i32 = 6;
+const XBR_EDGE_WEIGHT: i32 = 4;
+// distance under which xBR treats two colours as equal
+const XBR_EQUAL_DIST: i32 = 155;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scaler {
+    Nearest(usize),
+    Bilinear(usize),
+    Scale2x,
+    Scale3x,
+    CornerBlend2x,
+    Xbr2x,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Effect {
+    // darkening 0-1 of the gap between source lines
+    Scanlines(f32),
+    // 0-1 strength of an aperture grille of RGB stripes
+    CrtMask(f32),
+}
+
+#[derive(Clone)]
+pub struct PostProcess {
+    pub scaler: Scaler,
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            data: vec![0; width * height * RGB_SIZE],
+        }
+    }
+
+    pub fn from_rgb(width: usize, height: usize, data: &[u8]) -> Self {
+        Image {
+            width,
+            height,
+            data: data.to_vec(),
+        }
+    }
+
+    pub fn from_frame(frame: &Frame) -> Self {
+        Self::from_rgb(Frame::WIDTH, Frame::HIGHT, &frame.data)
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> u32 {
+        let base = (y * self.width + x) * RGB_SIZE;
+        u32::from_be_bytes([0, self.data[base], self.data[base + 1], self.data[base + 2]])
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        let base = (y * self.width + x) * RGB_SIZE;
+        let [_, r, g, b] = color.to_be_bytes();
+        self.data[base..base + RGB_SIZE].copy_from_slice(&[r, g, b]);
+    }
+
+    // out of range coordinates repeat the border pixels
+    fn clamped(&self, x: isize, y: isize) -> u32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.get_pixel(x, y)
+    }
+}
+
+impl Default for PostProcess {
+    fn default() -> Self {
+        PostProcess {
+            scaler: Scaler::Nearest(1),
+            effects: Vec::new(),
+        }
+    }
+}
+
+impl Scaler {
+    pub fn factor(&self) -> usize {
+        match self {
+            Scaler::Nearest(factor) | Scaler::Bilinear(factor) => (*factor).max(1),
+            Scaler::Scale2x | Scaler::CornerBlend2x | Scaler::Xbr2x => 2,
+            Scaler::Scale3x => 3,
+        }
+    }
+
+    pub fn apply(&self, src: &Image) -> Image {
+        match self {
+            Scaler::Nearest(_) => nearest(src, self.factor()),
+            Scaler::Bilinear(_) => bilinear(src, self.factor()),
+            Scaler::Scale2x => scale2x(src),
+            Scaler::Scale3x => scale3x(src),
+            Scaler::CornerBlend2x => corner_blend2x(src),
+            Scaler::Xbr2x => xbr2x(src),
+        }
+    }
+}
+
+impl Effect {
+    pub fn apply(&self, image: &mut Image, factor: usize) {
+        match self {
+            Effect::Scanlines(intensity) => scanlines(image, factor, *intensity),
+            Effect::CrtMask(strength) => crt_mask(image, *strength),
+        }
+    }
+}
+
+impl PostProcess {
+    pub fn apply(&self, src: &Image) -> Image {
+        let mut image = self.scaler.apply(src);
+        for effect in &self.effects {
+            effect.apply(&mut image, self.scaler.factor());
+        }
+        image
+    }
+}
+
+fn nearest(src: &Image, factor: usize) -> Image {
+    let mut dst = Image::new(src.width * factor, src.height * factor);
+    for y in 0..dst.height {
+        for x in 0..dst.width {
+            dst.set_pixel(x, y, src.get_pixel(x / factor, y / factor));
+        }
+    }
+    dst
+}
+
+fn bilinear(src: &Image, factor: usize) -> Image {
+    let mut dst = Image::new(src.width * factor, src.height * factor);
+    let to_src = |pos: usize| (pos as f32 + 0.5) / factor as f32 - 0.5;
+
+    for y in 0..dst.height {
+        let sy = to_src(y);
+        let (y0, fy) = (sy.floor() as isize, sy - sy.floor());
+        for x in 0..dst.width {
+            let sx = to_src(x);
+            let (x0, fx) = (sx.floor() as isize, sx - sx.floor());
+
+            let top = mix(src.clamped(x0, y0), src.clamped(x0 + 1, y0), fx);
+            let bottom = mix(src.clamped(x0, y0 + 1), src.clamped(x0 + 1, y0 + 1), fx);
+            dst.set_pixel(x, y, mix(top, bottom, fy));
+        }
+    }
+    dst
+}
+
+// AdvMAME2x: each output quarter copies an edge neighbour when two of them agree
+fn scale2x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 2, src.height * 2);
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (x, y) = (x as isize, y as isize);
+            let e = src.clamped(x, y);
+            let b = src.clamped(x, y - 1);
+            let d = src.clamped(x - 1, y);
+            let f = src.clamped(x + 1, y);
+            let h = src.clamped(x, y + 1);
+
+            let mut out = [e; 4];
+            if b != h && d != f {
+                if d == b {
+                    out[0] = d;
+                }
+                if b == f {
+                    out[1] = f;
+                }
+                if d == h {
+                    out[2] = d;
+                }
+                if h == f {
+                    out[3] = f;
+                }
+            }
+
+            let (dx, dy) = (x as usize * 2, y as usize * 2);
+            dst.set_pixel(dx, dy, out[0]);
+            dst.set_pixel(dx + 1, dy, out[1]);
+            dst.set_pixel(dx, dy + 1, out[2]);
+            dst.set_pixel(dx + 1, dy + 1, out[3]);
+        }
+    }
+    dst
+}
+
+// AdvMAME3x
+fn scale3x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 3, src.height * 3);
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (x, y) = (x as isize, y as isize);
+            let a = src.clamped(x - 1, y - 1);
+            let b = src.clamped(x, y - 1);
+            let c = src.clamped(x + 1, y - 1);
+            let d = src.clamped(x - 1, y);
+            let e = src.clamped(x, y);
+            let f = src.clamped(x + 1, y);
+            let g = src.clamped(x - 1, y + 1);
+            let h = src.clamped(x, y + 1);
+            let i = src.clamped(x + 1, y + 1);
+
+            let mut out = [e; 9];
+            if b != h && d != f {
+                out[0] = if d == b { d } else { e };
+                out[1] = if (d == b && e != c) || (b == f && e != a) {
+                    b
+                } else {
+                    e
+                };
+                out[2] = if b == f { f } else { e };
+                out[3] = if (d == b && e != g) || (d == h && e != a) {
+                    d
+                } else {
+                    e
+                };
+                out[5] = if (b == f && e != i) || (h == f && e != c) {
+                    f
+                } else {
+                    e
+                };
+                out[6] = if d == h { d } else { e };
+                out[7] = if (d == h && e != i) || (h == f && e != g) {
+                    h
+                } else {
+                    e
+                };
+                out[8] = if h == f { f } else { e };
+            }
+
+            for (i, color) in out.iter().enumerate() {
+                dst.set_pixel(x as usize * 3 + i % 3, y as usize * 3 + i / 3, *color);
+            }
+        }
+    }
+    dst
+}
+
+// not hq2x: it borrows the hqx YUV thresholds but replaces the lookup table with a few
+// corner rules, every quarter blends the centre with the two edge neighbours and the
+// diagonal around it, depending on which of them differ
+fn corner_blend2x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 2, src.height * 2);
+    const CORNERS: [(isize, isize); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (sx, sy) = (x as isize, y as isize);
+            let e = src.clamped(sx, sy);
+
+            for (quarter, (cx, cy)) in CORNERS.iter().enumerate() {
+                let horizontal = src.clamped(sx + cx, sy);
+                let vertical = src.clamped(sx, sy + cy);
+                let diagonal = src.clamped(sx + cx, sy + cy);
+
+                let edge_h = yuv_differ(e, horizontal);
+                let edge_v = yuv_differ(e, vertical);
+                let color = match (edge_h, edge_v) {
+                    // a diagonal line passing the corner, round it off
+                    (true, true) if !yuv_differ(horizontal, vertical) => {
+                        blend3(e, horizontal, vertical, 2, 1, 1)
+                    }
+                    (true, true) => blend3(e, horizontal, vertical, 6, 1, 1),
+                    (false, false) if yuv_differ(e, diagonal) => blend3(e, diagonal, e, 3, 1, 0),
+                    (false, false) => e,
+                    (true, false) => blend3(e, horizontal, e, 3, 1, 0),
+                    (false, true) => blend3(e, vertical, e, 3, 1, 0),
+                };
+
+                dst.set_pixel(x * 2 + quarter % 2, y * 2 + quarter / 2, color);
+            }
+        }
+    }
+    dst
+}
+
+// 2xBR level 1: compare the weighted edge strength across both diagonals of each
+// corner, and when the edge runs along it blend the corner towards the closer
+// neighbour; shallow and steep edges also spill into the quarter next to the corner
+fn xbr2x(src: &Image) -> Image {
+    let mut dst = Image::new(src.width * 2, src.height * 2);
+    // the (x, y) direction of each corner, in the order the reference filters them
+    const CORNERS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+    let quarter = |dx: isize, dy: isize| (dy > 0) as usize * 2 + (dx > 0) as usize;
+
+    for y in 0..src.height {
+        for x in 0..src.width {
+            let (sx, sy) = (x as isize, y as isize);
+            let e = src.clamped(sx, sy);
+            let mut out = [e; 4];
+
+            for (dx, dy) in CORNERS {
+                // neighbourhood mirrored so the corner being filled is always bottom right
+                let p = |u: isize, v: isize| src.clamped(sx + u * dx, sy + v * dy);
+                let (f, h, i) = (p(1, 0), p(0, 1), p(1, 1));
+                let (b, c, d, g) = (p(0, -1), p(1, -1), p(-1, 0), p(-1, 1));
+                let (f4, h5, i4, i5) = (p(2, 0), p(0, 2), p(2, 1), p(1, 2));
+
+                if e == f || e == h {
+                    continue;
+                }
+
+                let across = xbr_dist(e, c)
+                    + xbr_dist(e, g)
+                    + xbr_dist(i, f4)
+                    + xbr_dist(i, h5)
+                    + XBR_EDGE_WEIGHT * xbr_dist(h, f);
+                let along = xbr_dist(h, d)
+                    + xbr_dist(h, i5)
+                    + xbr_dist(f, i4)
+                    + xbr_dist(f, b)
+                    + XBR_EDGE_WEIGHT * xbr_dist(e, i);
+                let new = if xbr_dist(e, f) <= xbr_dist(e, h) {
+                    f
+                } else {
+                    h
+                };
+
+                let corner = quarter(dx, dy);
+                let (side, above) = (quarter(-dx, dy), quarter(dx, -dy));
+                let is_edge = (!xbr_eq(f, b) && !xbr_eq(h, d))
+                    || (xbr_eq(e, i) && !xbr_eq(f, i4) && !xbr_eq(h, i5))
+                    || xbr_eq(e, g)
+                    || xbr_eq(e, c);
+
+                if across < along && is_edge {
+                    let (ke, ki) = (xbr_dist(f, g), xbr_dist(h, c));
+                    let shallow = 2 * ke <= ki && e != g && d != g;
+                    let steep = ke >= 2 * ki && e != c && b != c;
+
+                    match (shallow, steep) {
+                        (true, true) => {
+                            out[corner] = mix(out[corner], new, 0.875);
+                            out[side] = mix(out[side], new, 0.25);
+                            out[above] = out[side];
+                        }
+                        (true, false) => {
+                            out[corner] = mix(out[corner], new, 0.75);
+                            out[side] = mix(out[side], new, 0.25);
+                        }
+                        (false, true) => {
+                            out[corner] = mix(out[corner], new, 0.75);
+                            out[above] = mix(out[above], new, 0.25);
+                        }
+                        (false, false) => out[corner] = mix(out[corner], new, 0.5),
+                    }
+                } else if across <= along {
+                    out[corner] = mix(out[corner], new, 0.25);
+                }
+            }
+
+            for (quarter, color) in out.into_iter().enumerate() {
+                dst.set_pixel(x * 2 + quarter % 2, y * 2 + quarter / 2, color);
+            }
+        }
+    }
+    dst
+}
+
+fn scanlines(image: &mut Image, factor: usize, intensity: f32) {
+    let keep = 1.0 - intensity.clamp(0.0, 1.0);
+
+    for y in 0..image.height {
+        let is_gap = if factor >= 2 {
+            y % factor == factor - 1
+        } else {
+            y % 2 == 1
+        };
+        if !is_gap {
+            continue;
+        }
+
+        let row = y * image.width * RGB_SIZE;
+        for channel in &mut image.data[row..row + image.width * RGB_SIZE] {
+            *channel = (*channel as f32 * keep) as u8;
+        }
+    }
+}
+
+fn crt_mask(image: &mut Image, strength: f32) {
+    let dim = 1.0 - strength.clamp(0.0, 1.0);
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let base = (y * image.width + x) * RGB_SIZE;
+            for channel in 0..RGB_SIZE {
+                if channel != x % RGB_SIZE {
+                    image.data[base + channel] = (image.data[base + channel] as f32 * dim) as u8;
+                }
+            }
+        }
+    }
+}
+
+fn channels(color: u32) -> [i32; 3] {
+    let [_, r, g, b] = color.to_be_bytes();
+    [r as i32, g as i32, b as i32]
+}
+
+fn yuv(color: u32) -> [i32; 3] {
+    let [r, g, b] = channels(color);
+    [
+        (r + g + b) / 3,
+        (r - b) / 4 + 128,
+        (2 * g - r - b) / 8 + 128,
+    ]
+}
+
+fn yuv_differ(a: u32, b: u32) -> bool {
+    let ([ya, ua, va], [yb, ub, vb]) = (yuv(a), yuv(b));
+    (ya - yb).abs() > Y_THRESHOLD || (ua - ub).abs() > U_THRESHOLD || (va - vb).abs() > V_THRESHOLD
+}
+
+fn xbr_dist(a: u32, b: u32) -> i32 {
+    let ([ya, ua, va], [yb, ub, vb]) = (yuv(a), yuv(b));
+    XBR_Y_WEIGHT * (ya - yb).abs() + XBR_U_WEIGHT * (ua - ub).abs() + XBR_V_WEIGHT * (va - vb).abs()
+}
+
+fn xbr_eq(a: u32, b: u32) -> bool {
+    xbr_dist(a, b) < XBR_EQUAL_DIST
+}
+
+fn mix(a: u32, b: u32, t: f32) -> u32 {
+    let (a, b) = (channels(a), channels(b));
+    let channel = |i: usize| (a[i] as f32 + (b[i] - a[i]) as f32 * t).round() as u32;
+    (channel(0) << 16) | (channel(1) << 8) | channel(2)
+}
+
+fn blend3(a: u32, b: u32, c: u32, wa: i32, wb: i32, wc: i32) -> u32 {
+    let (a, b, c) = (channels(a), channels(b), channels(c));
+    let total = wa + wb + wc;
+    let channel = |i: usize| ((a[i] * wa + b[i] * wb + c[i] * wc) / total) as u32;
+    (channel(0) << 16) | (channel(1) << 8) | channel(2)
+}
+
+#[cfg(test)]
+mod test;
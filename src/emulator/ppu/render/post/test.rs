@@ -0,0 +1,134 @@
+use super::*;
+
+const BLACK: u32 = 0x000000;
+const WHITE: u32 = 0xFFFFFF;
+const GREY: u32 = 0x808080;
+
+fn image(width: usize, height: usize, pixels: &[u32]) -> Image {
+    let mut image = Image::new(width, height);
+    for (i, color) in pixels.iter().enumerate() {
+        image.set_pixel(i % width, i / width, *color);
+    }
+    image
+}
+
+// white staircase below the main diagonal, black above
+fn diagonal(size: usize) -> Image {
+    let pixels: Vec<u32> = (0..size * size)
+        .map(|i| if i % size <= i / size { WHITE } else { BLACK })
+        .collect();
+    image(size, size, &pixels)
+}
+
+fn pixels(image: &Image) -> Vec<u32> {
+    (0..image.width * image.height)
+        .map(|i| image.get_pixel(i % image.width, i / image.width))
+        .collect()
+}
+
+#[test]
+fn nearest_repeats_pixels() {
+    let scaled = Scaler::Nearest(3).apply(&image(2, 1, &[BLACK, WHITE]));
+
+    assert_eq!((scaled.width, scaled.height), (6, 3));
+    assert_eq!(scaled.get_pixel(2, 2), BLACK);
+    assert_eq!(scaled.get_pixel(3, 0), WHITE);
+}
+
+#[test]
+fn bilinear_blends_between_pixels() {
+    let scaled = Scaler::Bilinear(2).apply(&image(2, 1, &[BLACK, WHITE]));
+
+    assert_eq!(scaled.get_pixel(0, 0), BLACK);
+    assert_eq!(scaled.get_pixel(3, 0), WHITE);
+    let [_, r, _, _] = scaled.get_pixel(1, 0).to_be_bytes();
+    assert!(r > 0x20 && r < 0x80);
+}
+
+#[test]
+fn flat_images_are_unchanged_by_every_scaler() {
+    let flat = image(4, 4, &[GREY; 16]);
+
+    for scaler in [
+        Scaler::Nearest(2),
+        Scaler::Bilinear(2),
+        Scaler::Scale2x,
+        Scaler::Scale3x,
+        Scaler::CornerBlend2x,
+        Scaler::Xbr2x,
+    ] {
+        let scaled = scaler.apply(&flat);
+        let factor = scaler.factor();
+        assert_eq!((scaled.width, scaled.height), (4 * factor, 4 * factor));
+        assert!(
+            pixels(&scaled).iter().all(|color| *color == GREY),
+            "{scaler:?}"
+        );
+    }
+}
+
+#[test]
+fn scale2x_rounds_diagonal_corners() {
+    let scaled = Scaler::Scale2x.apply(&diagonal(3));
+
+    // the black pixel right of the diagonal gets its bottom left quarter filled
+    assert_eq!(scaled.get_pixel(2, 1), WHITE);
+    assert_eq!(scaled.get_pixel(3, 0), BLACK);
+}
+
+#[test]
+fn scale3x_rounds_diagonal_corners() {
+    let scaled = Scaler::Scale3x.apply(&diagonal(3));
+
+    assert_eq!(scaled.get_pixel(3, 2), WHITE);
+    assert_eq!(scaled.get_pixel(5, 0), BLACK);
+}
+
+#[test]
+fn corner_blend_and_xbr_smooth_diagonals() {
+    for scaler in [Scaler::CornerBlend2x, Scaler::Xbr2x] {
+        let scaled = scaler.apply(&diagonal(4));
+        let blended = pixels(&scaled)
+            .iter()
+            .filter(|color| **color != BLACK && **color != WHITE)
+            .count();
+        assert!(blended > 0, "{scaler:?}");
+    }
+}
+
+#[test]
+fn xbr_keeps_straight_edges_sharp() {
+    // top half white, bottom half black
+    let pixels: Vec<u32> = (0..16).map(|i| if i < 8 { WHITE } else { BLACK }).collect();
+    let scaled = Scaler::Xbr2x.apply(&image(4, 4, &pixels));
+
+    for (i, color) in self::pixels(&scaled).into_iter().enumerate() {
+        let expected = if i < 32 { WHITE } else { BLACK };
+        assert_eq!(color, expected, "pixel {i}");
+    }
+}
+
+#[test]
+fn scanlines_darken_the_gap_rows() {
+    let post = PostProcess {
+        scaler: Scaler::Nearest(2),
+        effects: vec![Effect::Scanlines(0.5)],
+    };
+    let out = post.apply(&image(1, 1, &[WHITE]));
+
+    assert_eq!(out.get_pixel(0, 0), WHITE);
+    assert_eq!(out.get_pixel(0, 1), 0x7F7F7F);
+}
+
+#[test]
+fn crt_mask_keeps_one_channel_per_column() {
+    let post = PostProcess {
+        scaler: Scaler::Nearest(3),
+        effects: vec![Effect::CrtMask(1.0)],
+    };
+    let out = post.apply(&image(1, 1, &[WHITE]));
+
+    assert_eq!(out.get_pixel(0, 0), 0xFF0000);
+    assert_eq!(out.get_pixel(1, 0), 0x00FF00);
+    assert_eq!(out.get_pixel(2, 0), 0x0000FF);
+}
@@ -0,0 +1,165 @@
+use super::OAM_CACHE_SIZE;
+
+pub const SECONDARY_OAM_SIZE: usize = OAM_CACHE_SIZE * SPRITE_BYTES;
+pub const SPRITE_BYTES: usize = 4;
+
+const EMPTY_SLOT: u8 = 0xFF;
+const OAM_ADDR_BYTE_MASK: u16 = 0b0000_0011;
+const OAM_SIZE: u16 = 256;
+
+pub struct SpriteEval {
+    secondary_oam: [u8; SECONDARY_OAM_SIZE],
+    oam_indices: [u8; OAM_CACHE_SIZE],
+    latch: u8,
+    found: usize,
+    copy_byte: usize,
+    done: bool,
+    sprite_zero_found: bool,
+    first_check: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct SpriteUnit {
+    pub pattern_lo: u8,
+    pub pattern_hi: u8,
+    pub attr: u8,
+    pub x: u8,
+    pub oam_index: u8,
+}
+
+impl SpriteEval {
+    pub fn new() -> Self {
+        SpriteEval {
+            secondary_oam: [EMPTY_SLOT; SECONDARY_OAM_SIZE],
+            oam_indices: [0; OAM_CACHE_SIZE],
+            latch: EMPTY_SLOT,
+            found: 0,
+            copy_byte: 0,
+            done: false,
+            sprite_zero_found: false,
+            first_check: true,
+        }
+    }
+
+    // dots 1-64: secondary oam is filled with $FF one byte every 2 dots
+    pub fn clear(&mut self, cycle: usize) {
+        self.latch = EMPTY_SLOT;
+        if (cycle & 1) == 0 {
+            self.secondary_oam[cycle / 2 - 1] = EMPTY_SLOT;
+        }
+        if cycle == 1 {
+            self.reset();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.found = 0;
+        self.copy_byte = 0;
+        self.done = false;
+        self.sprite_zero_found = false;
+        self.first_check = true;
+    }
+
+    // dots 65-256: odd dots read primary oam, even dots write secondary oam.
+    // OAMADDR is the evaluation pointer, so a non zero start misaligns the scan.
+    // returns true when the sprite overflow flag should be set.
+    pub fn step(
+        &mut self,
+        cycle: usize,
+        oam: &[u8],
+        oam_addr: &mut u8,
+        scanline: u16,
+        height: u8,
+    ) -> bool {
+        if (cycle & 1) == 1 {
+            self.latch = oam[*oam_addr as usize];
+            return false;
+        }
+
+        let addr = *oam_addr as u16;
+        let in_range = {
+            let diff = scanline as i16 - self.latch as i16;
+            (diff >= 0) && (diff < height as i16)
+        };
+        let mut overflow = false;
+
+        let next_addr = if self.done {
+            addr + SPRITE_BYTES as u16
+        } else if self.found < OAM_CACHE_SIZE {
+            self.secondary_oam[self.found * SPRITE_BYTES + self.copy_byte] = self.latch;
+
+            if self.copy_byte == 0 {
+                if self.first_check && in_range {
+                    self.sprite_zero_found = true;
+                }
+                self.first_check = false;
+
+                if in_range {
+                    self.oam_indices[self.found] = (addr / SPRITE_BYTES as u16) as u8;
+                    self.copy_byte = 1;
+                    addr + 1
+                } else {
+                    addr + SPRITE_BYTES as u16
+                }
+            } else {
+                self.copy_byte += 1;
+                if self.copy_byte == SPRITE_BYTES {
+                    self.copy_byte = 0;
+                    self.found += 1;
+                }
+                addr + 1
+            }
+        } else if in_range {
+            overflow = true;
+            self.done = true;
+            addr + 1
+        } else {
+            // hardware bug: the byte index is incremented together with the sprite index
+            ((addr + SPRITE_BYTES as u16) & !OAM_ADDR_BYTE_MASK) | ((addr + 1) & OAM_ADDR_BYTE_MASK)
+        };
+
+        if next_addr >= OAM_SIZE {
+            self.done = true;
+        }
+        *oam_addr = next_addr as u8;
+
+        overflow
+    }
+
+    pub fn get_latch(&self) -> u8 {
+        self.latch
+    }
+
+    pub fn get_found(&self) -> usize {
+        self.found
+    }
+
+    pub fn is_sprite_zero_found(&self) -> bool {
+        self.sprite_zero_found
+    }
+
+    pub fn get_oam_index(&self, slot: usize) -> u8 {
+        self.oam_indices[slot]
+    }
+
+    pub fn get_sprite(&self, slot: usize) -> &[u8] {
+        &self.secondary_oam[slot * SPRITE_BYTES..(slot + 1) * SPRITE_BYTES]
+    }
+
+    // dots 257-320: the fetch unit walks secondary oam, OAMDATA reads see these bytes
+    pub fn fetch_read(&mut self, slot: usize, byte: usize) {
+        self.latch = self.secondary_oam[slot * SPRITE_BYTES + byte.min(SPRITE_BYTES - 1)];
+    }
+}
+
+impl SpriteUnit {
+    pub fn new() -> Self {
+        SpriteUnit {
+            pattern_lo: 0,
+            pattern_hi: 0,
+            attr: 0,
+            x: EMPTY_SLOT,
+            oam_index: 0,
+        }
+    }
+}
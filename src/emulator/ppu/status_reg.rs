@@ -38,6 +38,14 @@ impl StatusReg {
         self.flags |= SPRITE_0_HIT_FLAG;
     }
 
+    pub fn set_sprite_overflow(&mut self) {
+        self.flags |= SPRITE_OVERFLOW_FLAG;
+    }
+
+    pub fn unset_sprite_overflow(&mut self) {
+        self.flags &= !SPRITE_OVERFLOW_FLAG;
+    }
+
     pub fn is_in_vblank(&self) -> bool {
         (self.flags & VBLANK_FLAG) != 0
     }
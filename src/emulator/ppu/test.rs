@@ -0,0 +1,484 @@
+use super::mask_reg::{EMPHASIZE_RED_FLAG, GREY_SCALE_FLAG};
+use super::nametables::{FOUR_SCREEN_VRAM_SIZE, NAME_TABLE_SIZE};
+use super::render::pallete_table::SYSTEM_PALLETE;
+use super::status_reg::SPRITE_OVERFLOW_FLAG;
+use super::*;
+use crate::emulator::rom::Mirroring;
+use crate::emulator::test_helpers::{
+    SOLID_TILE, read_byte, reset_scroll, solid_tile_ppu, write_byte,
+};
+
+const CHR_SIZE: usize = 0x2000;
+const HIDDEN_Y: u8 = 0xF0;
+const BACKDROP_COLOR: u8 = 0x0F;
+const SPRITE_COLOR: u8 = 0x16;
+const SHOW_ALL: u8 = 0b0001_1110;
+
+fn test_ppu(sprites: &[(u8, u8)]) -> Ppu {
+    let mut ppu = solid_tile_ppu(Mirroring::Horizontal);
+
+    write_byte(&mut ppu, 0x3F00, BACKDROP_COLOR);
+    write_byte(&mut ppu, 0x3F11, SPRITE_COLOR);
+
+    let mut oam = [HIDDEN_Y; OAM_DATA_SIZE];
+    for (i, (y, x)) in sprites.iter().enumerate() {
+        oam[i * 4..i * 4 + 4].copy_from_slice(&[*y, SOLID_TILE, 0, *x]);
+    }
+    ppu.write_to_oam_dma(&oam);
+    ppu.write_to_mask(SHOW_ALL);
+
+    ppu
+}
+
+fn run_until(ppu: &mut Ppu, scanline: u16, dot: usize) {
+    while (ppu.get_scanline() != scanline) || (ppu.get_dot() != dot) {
+        ppu.tick();
+    }
+}
+
+fn pixel(ppu: &Ppu, x: usize, y: usize) -> (u8, u8, u8) {
+    let base = (y * Frame::WIDTH + x) * 3;
+    let data = &ppu.screen.data;
+    (data[base], data[base + 1], data[base + 2])
+}
+
+#[test]
+fn nine_sprites_on_a_line_set_overflow() {
+    let sprites: Vec<(u8, u8)> = (0..9).map(|i| (50, i * 10)).collect();
+    let mut ppu = test_ppu(&sprites);
+
+    run_until(&mut ppu, 240, 0);
+    assert_ne!(ppu.read_status() & SPRITE_OVERFLOW_FLAG, 0);
+}
+
+#[test]
+fn eight_sprites_do_not_set_overflow() {
+    let sprites: Vec<(u8, u8)> = (0..8).map(|i| (50, i * 10)).collect();
+    let mut ppu = test_ppu(&sprites);
+
+    run_until(&mut ppu, 240, 0);
+    assert_eq!(ppu.read_status() & SPRITE_OVERFLOW_FLAG, 0);
+}
+
+#[test]
+fn sprites_past_the_eighth_are_not_drawn() {
+    let sprites: Vec<(u8, u8)> = (0..9).map(|i| (100, i * 10 + 8)).collect();
+    let mut ppu = test_ppu(&sprites);
+
+    run_until(&mut ppu, 240, 0);
+    let sprite = SYSTEM_PALLETE[SPRITE_COLOR as usize];
+    let backdrop = SYSTEM_PALLETE[BACKDROP_COLOR as usize];
+
+    assert_eq!(pixel(&ppu, 8, 101), sprite);
+    assert_eq!(pixel(&ppu, 7, 101), backdrop);
+    assert_eq!(pixel(&ppu, 8, 100), backdrop);
+    assert_eq!(pixel(&ppu, 78, 101), sprite);
+    assert_eq!(pixel(&ppu, 88, 101), backdrop);
+}
+
+#[test]
+fn oam_data_reads_ff_while_clearing_secondary_oam() {
+    let mut ppu = test_ppu(&[(0, 0)]);
+
+    run_until(&mut ppu, 5, 30);
+    assert_eq!(ppu.read_oam_data(), 0xFF);
+}
+
+#[test]
+fn overflow_scan_reads_diagonally_through_oam() {
+    let sprites: Vec<(u8, u8)> = (0..8).map(|i| (50, i * 10)).collect();
+    let mut ppu = test_ppu(&sprites);
+
+    // only 8 sprites are on line 50, but the buggy scan reads sprite 9's tile byte as its y
+    let mut oam = [HIDDEN_Y; OAM_DATA_SIZE];
+    for (i, (y, x)) in sprites.iter().enumerate() {
+        oam[i * 4..i * 4 + 4].copy_from_slice(&[*y, SOLID_TILE, 0, *x]);
+    }
+    oam[9 * 4 + 1] = 50;
+    ppu.write_to_oam_dma(&oam);
+
+    run_until(&mut ppu, 240, 0);
+    assert_ne!(ppu.read_status() & SPRITE_OVERFLOW_FLAG, 0);
+}
+
+#[test]
+fn sprite_limit_can_be_lifted_without_hiding_overflow() {
+    let sprites: Vec<(u8, u8)> = (0..9).map(|i| (100, i * 10 + 8)).collect();
+    let mut ppu = test_ppu(&sprites);
+    ppu.set_sprite_limit(false);
+
+    run_until(&mut ppu, 240, 0);
+    let sprite = SYSTEM_PALLETE[SPRITE_COLOR as usize];
+
+    assert_eq!(pixel(&ppu, 88, 101), sprite);
+    assert_ne!(ppu.read_status() & SPRITE_OVERFLOW_FLAG, 0);
+}
+
+#[test]
+fn greyscale_keeps_only_the_luma_column() {
+    let mut ppu = test_ppu(&[(100, 8)]);
+    ppu.write_to_mask(SHOW_ALL | GREY_SCALE_FLAG);
+
+    run_until(&mut ppu, 240, 0);
+    let grey = SYSTEM_PALLETE[(SPRITE_COLOR & GREYSCALE_MASK) as usize];
+
+    assert_eq!(pixel(&ppu, 8, 101), grey);
+}
+
+#[test]
+fn emphasis_darkens_the_other_channels() {
+    let mut ppu = test_ppu(&[(100, 8)]);
+    ppu.write_to_mask(SHOW_ALL | EMPHASIZE_RED_FLAG);
+
+    run_until(&mut ppu, 240, 0);
+    let (r, g, _) = SYSTEM_PALLETE[SPRITE_COLOR as usize];
+    let (er, eg, _) = pixel(&ppu, 8, 101);
+
+    assert_eq!(er, r);
+    assert!(eg < g);
+}
+
+#[test]
+fn index_buffer_tracks_colour_and_emphasis() {
+    let mut ppu = test_ppu(&[(100, 8)]);
+    ppu.write_to_mask(SHOW_ALL | EMPHASIZE_RED_FLAG);
+
+    run_until(&mut ppu, 240, 0);
+    let index = |x: usize, y: usize| ppu.screen_indices.data[y * Frame::WIDTH + x];
+    let red_emphasis = 1 << IndexFrame::EMPHASIS_SHIFT;
+
+    assert_eq!(index(8, 101), SPRITE_COLOR as u16 | red_emphasis);
+    assert_eq!(index(7, 101), BACKDROP_COLOR as u16 | red_emphasis);
+}
+
+#[test]
+fn status_low_bits_come_from_the_bus() {
+    let mut ppu = test_ppu(&[]);
+
+    ppu.write_to_oam_addr(0b1011_0101);
+    assert_eq!(ppu.read_status(), 0b0001_0101);
+}
+
+#[test]
+fn write_only_registers_read_back_the_last_write() {
+    let mut ppu = test_ppu(&[]);
+
+    ppu.write_to_scroll(0xA5);
+    assert_eq!(ppu.read_open_bus(), 0xA5);
+
+    ppu.write_to_status(0x3C);
+    assert_eq!(ppu.read_open_bus(), 0x3C);
+}
+
+#[test]
+fn open_bus_decays() {
+    let mut ppu = test_ppu(&[]);
+
+    ppu.write_to_ppu_addr(0x3F);
+    ppu.write_to_ppu_addr(0x00);
+    ppu.write_to_status(0xFF);
+    ppu.frame_count += io_latch::DECAY_FRAMES / 2;
+
+    // a palette read refreshes the low 6 bits only
+    assert_eq!(ppu.read_data(), 0xC0 | BACKDROP_COLOR);
+
+    ppu.frame_count += io_latch::DECAY_FRAMES / 2;
+    assert_eq!(ppu.read_open_bus(), BACKDROP_COLOR);
+
+    ppu.frame_count += io_latch::DECAY_FRAMES;
+    assert_eq!(ppu.read_open_bus(), 0);
+}
+
+#[test]
+fn palette_reads_bypass_the_buffer() {
+    let mut ppu = test_ppu(&[]);
+
+    // the nametable byte under $3F00 is $2F00
+    ppu.write_to_ppu_addr(0x2F);
+    ppu.write_to_ppu_addr(0x00);
+    ppu.write_to_data(0x42);
+
+    ppu.write_to_ppu_addr(0x3F);
+    ppu.write_to_ppu_addr(0x00);
+    assert_eq!(ppu.read_data(), BACKDROP_COLOR);
+
+    ppu.write_to_ppu_addr(0x20);
+    ppu.write_to_ppu_addr(0x00);
+    assert_eq!(ppu.read_data(), 0x42);
+}
+
+#[test]
+fn palette_reads_are_greyscaled() {
+    let mut ppu = test_ppu(&[]);
+
+    ppu.write_to_mask(GREY_SCALE_FLAG);
+    ppu.write_to_ppu_addr(0x3F);
+    ppu.write_to_ppu_addr(0x11);
+    assert_eq!(
+        ppu.read_data() & PALETTE_DATA_MASK,
+        SPRITE_COLOR & GREYSCALE_MASK
+    );
+}
+
+#[test]
+fn upper_nametable_space_mirrors_the_nametables() {
+    let mut ppu = test_ppu(&[]);
+
+    write_byte(&mut ppu, 0x3123, 0x77);
+    assert_eq!(read_byte(&mut ppu, 0x2123), 0x77);
+
+    write_byte(&mut ppu, 0x2456, 0x99);
+    assert_eq!(read_byte(&mut ppu, 0x3456), 0x99);
+}
+
+#[test]
+fn four_screen_nametables_are_distinct() {
+    let nametables = Nametables::new(Mirroring::FourScreen, vec![0; FOUR_SCREEN_VRAM_SIZE]);
+    let mut ppu = Ppu::new(vec![0; CHR_SIZE], nametables);
+
+    for table in 0..4 {
+        write_byte(
+            &mut ppu,
+            0x2000 + (table * NAME_TABLE_SIZE) as u16,
+            table as u8 + 1,
+        );
+    }
+    for table in 0..4 {
+        assert_eq!(
+            read_byte(&mut ppu, 0x2000 + (table * NAME_TABLE_SIZE) as u16),
+            table as u8 + 1
+        );
+    }
+}
+
+#[test]
+fn missing_cartridge_vram_reads_zero() {
+    let mut ppu = Ppu::new(
+        vec![0; CHR_SIZE],
+        Nametables::new(Mirroring::FourScreen, Vec::new()),
+    );
+
+    write_byte(&mut ppu, 0x2800, 0x33);
+    assert_eq!(read_byte(&mut ppu, 0x2800), 0);
+}
+
+#[test]
+fn event_log_keeps_the_last_frame() {
+    let mut ppu = test_ppu(&[]);
+    ppu.enable_event_log();
+
+    run_until(&mut ppu, 100, 50);
+    ppu.log_write(0x2005, 0x12);
+    assert!(ppu.get_event_log().unwrap().get_events().is_empty());
+
+    run_until(&mut ppu, 241, 2);
+    let log = ppu.get_event_log().unwrap();
+    assert_eq!(
+        log.get_events(),
+        &[PpuEvent {
+            scanline: 100,
+            dot: 50,
+            addr: 0x2005,
+            value: 0x12,
+        }]
+    );
+    assert_eq!(log.events_near(101, 52, 2).len(), 1);
+    assert!(log.events_near(100, 53, 2).is_empty());
+}
+
+const BG_COLOR: u8 = 0x21;
+
+// an opaque background tile under the 8x8 block at (48, 48)
+fn with_background_tile(ppu: &mut Ppu) {
+    write_byte(ppu, 0x3F01, BG_COLOR);
+    write_byte(ppu, 0x2000 + 6 * 32 + 6, SOLID_TILE);
+    reset_scroll(ppu);
+}
+
+#[test]
+fn hidden_background_shows_the_backdrop() {
+    let mut ppu = test_ppu(&[]);
+    with_background_tile(&mut ppu);
+    let mut layers = Layers::default();
+    layers.hide_background = true;
+    ppu.set_layers(layers);
+
+    run_until(&mut ppu, 240, 0);
+    assert_eq!(pixel(&ppu, 52, 52), SYSTEM_PALLETE[BACKDROP_COLOR as usize]);
+}
+
+#[test]
+fn hidden_sprite_still_hits() {
+    let mut ppu = test_ppu(&[(50, 50), (50, 54)]);
+    with_background_tile(&mut ppu);
+    let mut layers = Layers::default();
+    layers.hide_sprite(0);
+    ppu.set_layers(layers);
+
+    run_until(&mut ppu, 240, 0);
+    assert_eq!(pixel(&ppu, 52, 52), SYSTEM_PALLETE[BG_COLOR as usize]);
+    assert_eq!(pixel(&ppu, 56, 52), SYSTEM_PALLETE[SPRITE_COLOR as usize]);
+    assert_ne!(ppu.read_status() & SPRITE_0_HIT_FLAG, 0);
+}
+
+#[test]
+fn hidden_sprites_layer() {
+    let mut ppu = test_ppu(&[(50, 50)]);
+    let mut layers = Layers::default();
+    layers.hide_sprites = true;
+    ppu.set_layers(layers);
+
+    run_until(&mut ppu, 240, 0);
+    assert_eq!(pixel(&ppu, 52, 52), SYSTEM_PALLETE[BACKDROP_COLOR as usize]);
+}
+
+const SPLIT_SCANLINE: u16 = 10;
+const SPLIT_TILE_ADDR: u16 = 0x2000 + 32 + 20;
+
+// dot 104 already bumped coarse x, the write lands before the fetch at dot 105
+fn split_mid_scanline(ppu: &mut Ppu) {
+    run_until(ppu, SPLIT_SCANLINE, 105);
+    ppu.read_status();
+    ppu.write_to_ppu_addr((SPLIT_TILE_ADDR >> 8) as u8);
+    ppu.write_to_ppu_addr(SPLIT_TILE_ADDR as u8);
+}
+
+fn split_ppu() -> Ppu {
+    let mut ppu = test_ppu(&[]);
+    write_byte(&mut ppu, 0x3F01, BG_COLOR);
+    write_byte(&mut ppu, SPLIT_TILE_ADDR, SOLID_TILE);
+    reset_scroll(&mut ppu);
+    ppu
+}
+
+#[test]
+fn fetch_background_follows_a_mid_scanline_address_write() {
+    let mut ppu = split_ppu();
+    split_mid_scanline(&mut ppu);
+    assert_ne!(ppu.bg_shifter.next_tile_id(), SOLID_TILE);
+
+    ppu.tick();
+    assert_eq!(ppu.bg_shifter.next_tile_id(), SOLID_TILE);
+    assert_eq!(ppu.internal_regs.get_v(), SPLIT_TILE_ADDR);
+
+    run_until(&mut ppu, SPLIT_SCANLINE, 113);
+    assert_eq!(ppu.internal_regs.get_v(), SPLIT_TILE_ADDR + 1);
+}
+
+#[test]
+fn mid_scanline_split_is_drawn_two_tiles_later() {
+    let mut ppu = split_ppu();
+    split_mid_scanline(&mut ppu);
+
+    run_until(&mut ppu, SPLIT_SCANLINE + 1, 0);
+    let y = SPLIT_SCANLINE as usize;
+    let backdrop = SYSTEM_PALLETE[BACKDROP_COLOR as usize];
+    let background = SYSTEM_PALLETE[BG_COLOR as usize];
+    assert_eq!(pixel(&ppu, 119, y), backdrop);
+    for x in 120..128 {
+        assert_eq!(pixel(&ppu, x, y), background, "x {x}");
+    }
+    assert_eq!(pixel(&ppu, 128, y), backdrop);
+}
+
+#[test]
+fn fine_x_write_shifts_the_next_pixels() {
+    let mut ppu = split_ppu();
+    split_mid_scanline(&mut ppu);
+    run_until(&mut ppu, SPLIT_SCANLINE, 117);
+    ppu.write_to_scroll(0b011);
+
+    run_until(&mut ppu, SPLIT_SCANLINE + 1, 0);
+    let y = SPLIT_SCANLINE as usize;
+    let backdrop = SYSTEM_PALLETE[BACKDROP_COLOR as usize];
+    let background = SYSTEM_PALLETE[BG_COLOR as usize];
+    assert_eq!(pixel(&ppu, 116, y), backdrop);
+    // fine x 3 pulls the tile 3 pixels left
+    for x in 117..125 {
+        assert_eq!(pixel(&ppu, x, y), background, "x {x}");
+    }
+    assert_eq!(pixel(&ppu, 125, y), backdrop);
+}
+
+#[test]
+fn sprites_can_be_shown_again() {
+    let mut layers = Layers::default();
+    layers.hide_sprite(3);
+    layers.hide_sprite(5);
+    layers.show_sprite(3);
+    assert!(!layers.is_sprite_hidden(3));
+    assert!(layers.is_sprite_hidden(5));
+
+    layers.toggle_sprite(5);
+    layers.toggle_sprite(7);
+    assert!(!layers.is_sprite_hidden(5));
+    assert!(layers.is_sprite_hidden(7));
+    assert_eq!(layers, {
+        let mut expected = Layers::default();
+        expected.hide_sprite(7);
+        expected
+    });
+}
+
+#[test]
+fn shown_sprite_is_drawn_again() {
+    let mut ppu = test_ppu(&[(50, 50)]);
+    let mut layers = Layers::default();
+    layers.hide_sprite(0);
+    ppu.set_layers(layers);
+    run_until(&mut ppu, 240, 0);
+    assert_eq!(pixel(&ppu, 52, 52), SYSTEM_PALLETE[BACKDROP_COLOR as usize]);
+
+    layers.toggle_sprite(0);
+    ppu.set_layers(layers);
+    run_until(&mut ppu, 241, 0);
+    run_until(&mut ppu, 240, 0);
+    assert_eq!(pixel(&ppu, 52, 52), SYSTEM_PALLETE[SPRITE_COLOR as usize]);
+}
+
+fn numbered_oam(ppu: &mut Ppu) {
+    let mut oam = [0; OAM_DATA_SIZE];
+    for (i, byte) in oam.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    ppu.write_to_oam_dma(&oam);
+}
+
+#[test]
+fn oam_addr_past_the_first_row_corrupts_oam() {
+    let mut ppu = test_ppu(&[]);
+    numbered_oam(&mut ppu);
+    let before = ppu.get_oam().to_vec();
+    run_until(&mut ppu, 250, 0);
+    ppu.write_to_oam_addr(0x2B);
+
+    run_until(&mut ppu, PRE_RENDER_SCANLINE, 2);
+    let oam = ppu.get_oam();
+    assert_eq!(&oam[..8], &before[0x28..0x30]);
+    assert_eq!(&oam[8..], &before[8..]);
+}
+
+#[test]
+fn oam_addr_in_the_first_row_leaves_oam_alone() {
+    let mut ppu = test_ppu(&[]);
+    numbered_oam(&mut ppu);
+    let before = ppu.get_oam().to_vec();
+    run_until(&mut ppu, 250, 0);
+    ppu.write_to_oam_addr(0x07);
+
+    run_until(&mut ppu, PRE_RENDER_SCANLINE, 2);
+    assert_eq!(ppu.get_oam(), &before[..]);
+}
+
+#[test]
+fn oam_is_not_corrupted_while_rendering_is_off() {
+    let mut ppu = test_ppu(&[]);
+    numbered_oam(&mut ppu);
+    let before = ppu.get_oam().to_vec();
+    ppu.write_to_mask(0);
+    run_until(&mut ppu, 250, 0);
+    ppu.write_to_oam_addr(0x2B);
+
+    run_until(&mut ppu, PRE_RENDER_SCANLINE, 2);
+    assert_eq!(ppu.get_oam(), &before[..]);
+}
@@ -0,0 +1,99 @@
+mod wav;
+mod y4m;
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use super::ppu::render::export::Capture;
+use super::ppu::render::frame::Frame;
+pub use wav::WavWriter;
+pub use y4m::Y4mWriter;
+
+// a frame is 29780.5 CPU cycles, so frames come at 1789773 / 29780.5 Hz,
+// the same clock the APU samples are taken from
+pub const FRAME_RATE_NUMERATOR: u32 = 3_579_546;
+pub const FRAME_RATE_DENOMINATOR: u32 = 59_561;
+
+pub struct Recorder {
+    video: Y4mWriter<BufWriter<File>>,
+    audio: WavWriter<BufWriter<File>>,
+    capture: Capture,
+    started: bool,
+    frames: usize,
+    samples: usize,
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    pub fn to_files(
+        video_path: &str,
+        audio_path: &str,
+        sample_rate: u32,
+        capture: Capture,
+    ) -> io::Result<Self> {
+        let (width, height) = capture.size();
+        let video = Y4mWriter::new(
+            BufWriter::new(File::create(video_path)?),
+            width,
+            height,
+            (FRAME_RATE_NUMERATOR, FRAME_RATE_DENOMINATOR),
+        )?;
+        let audio = WavWriter::new(BufWriter::new(File::create(audio_path)?), sample_rate)?;
+
+        Ok(Recorder {
+            video,
+            audio,
+            capture,
+            started: false,
+            frames: 0,
+            samples: 0,
+            error: None,
+        })
+    }
+
+    // both streams start at a frame boundary, so sample 0 plays with frame 0
+    pub fn push_frame(&mut self, frame: &Frame) {
+        if self.error.is_some() {
+            return;
+        }
+        self.started = true;
+        self.frames += 1;
+        let result = self.video.write_frame(&self.capture.image(frame).data);
+        self.keep_error(result);
+    }
+
+    pub fn push_sample(&mut self, sample: f32) {
+        if !self.started || self.error.is_some() {
+            return;
+        }
+        self.samples += 1;
+        let result = self.audio.write_sample(sample);
+        self.keep_error(result);
+    }
+
+    pub fn get_frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn get_samples(&self) -> usize {
+        self.samples
+    }
+
+    // the first write error stops the recording and is reported here
+    pub fn finish(self) -> io::Result<()> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.video.finish()?;
+        self.audio.finish()
+    }
+
+    fn keep_error(&mut self, result: io::Result<()>) {
+        if let Err(err) = result {
+            self.error.get_or_insert(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
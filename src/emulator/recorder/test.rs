@@ -0,0 +1,168 @@
+use std::fs;
+use std::io::Cursor;
+
+use super::*;
+use crate::emulator::apu::DEFAULT_SAMPLE_RATE;
+use crate::emulator::bus::Bus;
+use crate::emulator::memory::MemAccess;
+use crate::emulator::ppu::render::framing::Overscan;
+use crate::emulator::ppu::render::post::{PostProcess, Scaler};
+use crate::emulator::rom::Rom;
+use crate::emulator::test_helpers::temp_file;
+
+const RECORD_DIR: &str = "recorder";
+const PPU_CTRL: u16 = 0x2000;
+const GENERATE_NMI: u8 = 0b1000_0000;
+const Y4M_HEADER: &str = "YUV4MPEG2 W2 H1 F60:1 Ip A1:1 C444 XCOLORRANGE=FULL\n";
+
+fn record_paths(name: &str) -> (String, String) {
+    (
+        temp_file(RECORD_DIR, &format!("{name}.y4m")),
+        temp_file(RECORD_DIR, &format!("{name}.wav")),
+    )
+}
+
+fn empty_rom() -> Rom {
+    const PRG_ROM_SIZE: usize = 0x4000;
+    const CHR_ROM_SIZE: usize = 0x2000;
+
+    let mut raw = vec![b'N', b'E', b'S', 0x1A, 1, 1];
+    raw.resize(16, 0);
+    raw.resize(16 + PRG_ROM_SIZE + CHR_ROM_SIZE, 0);
+    Rom::new(&raw).unwrap()
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn y4m_writes_planar_full_range_frames() {
+    let mut out = Vec::new();
+    let mut video = Y4mWriter::new(&mut out, 2, 1, (60, 1)).unwrap();
+    video.write_frame(&[0, 0, 0, 255, 255, 255]).unwrap();
+    video.finish().unwrap();
+
+    let (header, frame) = out.split_at(Y4M_HEADER.len());
+    assert_eq!(header, Y4M_HEADER.as_bytes());
+    assert_eq!(frame, b"FRAME\n\x00\xFF\x80\x80\x80\x80");
+}
+
+#[test]
+fn ycbcr_keeps_primaries_apart() {
+    assert_eq!(y4m::to_ycbcr(255, 0, 0), (76, 85, 255));
+    assert_eq!(y4m::to_ycbcr(0, 0, 255), (29, 255, 107));
+}
+
+#[test]
+fn wav_sizes_are_filled_in_on_finish() {
+    let mut out = Cursor::new(Vec::new());
+    let mut audio = WavWriter::new(&mut out, DEFAULT_SAMPLE_RATE).unwrap();
+    for sample in [0.0, 1.0, -1.0, 2.0] {
+        audio.write_sample(sample).unwrap();
+    }
+    audio.finish().unwrap();
+
+    let data = out.into_inner();
+    assert_eq!(&data[0..4], b"RIFF");
+    assert_eq!(u32_at(&data, 4), data.len() as u32 - 8);
+    assert_eq!(u32_at(&data, 24), DEFAULT_SAMPLE_RATE);
+    assert_eq!(u32_at(&data, 40), 8);
+    assert_eq!(
+        &data[44..],
+        [0x00, 0x00, 0xFF, 0x7F, 0x01, 0x80, 0xFF, 0x7F]
+    );
+}
+
+#[test]
+fn samples_before_the_first_frame_are_dropped() {
+    let (video_path, audio_path) = record_paths("first_frame");
+    let mut recorder = Recorder::to_files(
+        &video_path,
+        &audio_path,
+        DEFAULT_SAMPLE_RATE,
+        Capture::default(),
+    )
+    .unwrap();
+
+    recorder.push_sample(0.5);
+    recorder.push_frame(&Frame::new());
+    recorder.push_sample(0.5);
+    assert_eq!((recorder.get_frames(), recorder.get_samples()), (1, 1));
+    recorder.finish().unwrap();
+
+    let video = fs::read(video_path).unwrap();
+    assert_eq!(video.iter().filter(|&&byte| byte == b'\n').count(), 2);
+    assert_eq!(fs::read(audio_path).unwrap().len(), 44 + 2);
+}
+
+// the samples between the first and the last frame span frames - 1 frame periods
+fn assert_in_sync(bus: &Bus, frames: usize, sample_rate: u32) {
+    let samples = bus.get_recorder().unwrap().get_samples() as f64;
+    let expected = (frames - 1) as f64 * sample_rate as f64 * FRAME_RATE_DENOMINATOR as f64
+        / FRAME_RATE_NUMERATOR as f64;
+    assert!((samples - expected).abs() <= 1.0, "{samples} vs {expected}");
+}
+
+fn record_frames(name: &str, ctrl: u8, frames: usize) -> Bus<'static> {
+    let (video_path, audio_path) = record_paths(name);
+    let mut bus = Bus::new(empty_rom(), |_, _| {});
+    bus.mem_write(PPU_CTRL, ctrl);
+    let sample_rate = bus.get_apu().get_sample_rate();
+    bus.start_recording(
+        Recorder::to_files(&video_path, &audio_path, sample_rate, Capture::default()).unwrap(),
+    );
+
+    // the recording starts at the first vblank, so it has one frame per PPU frame
+    while bus.get_ppu().get_frame_count() < frames {
+        bus.tick(1);
+        bus.poll_nmi_status();
+    }
+    assert_eq!(bus.get_recorder().unwrap().get_frames(), frames);
+    assert_in_sync(&bus, frames, sample_rate);
+    bus
+}
+
+#[test]
+fn audio_keeps_pace_with_video() {
+    let mut bus = record_frames("sync", GENERATE_NMI, 4);
+    bus.stop_recording().unwrap();
+    assert!(!bus.is_recording());
+}
+
+#[test]
+fn frames_are_recorded_with_nmi_disabled() {
+    let mut bus = record_frames("no_nmi", 0, 4);
+    bus.stop_recording().unwrap();
+}
+
+#[test]
+fn frames_are_recorded_cropped_and_post_processed() {
+    let (video_path, audio_path) = record_paths("scaled");
+    let capture = Capture {
+        overscan: Overscan::ntsc_safe_area(),
+        post_process: PostProcess {
+            scaler: Scaler::Nearest(2),
+            effects: Vec::new(),
+        },
+    };
+    let mut frame = Frame::new();
+    frame.set_pixel(0, 8, (255, 255, 255));
+
+    let mut recorder =
+        Recorder::to_files(&video_path, &audio_path, DEFAULT_SAMPLE_RATE, capture).unwrap();
+    recorder.push_frame(&frame);
+    recorder.finish().unwrap();
+
+    // the header carries the cropped size, 8 lines off the top and the bottom
+    let (width, height) = (2 * Frame::WIDTH, 2 * (Frame::HIGHT - 16));
+    let video = fs::read(video_path).unwrap();
+    let header = format!(
+        "YUV4MPEG2 W{width} H{height} F{FRAME_RATE_NUMERATOR}:{FRAME_RATE_DENOMINATOR} Ip A1:1 C444 XCOLORRANGE=FULL\nFRAME\n"
+    );
+    assert!(video.starts_with(header.as_bytes()));
+    let luma = &video[header.len()..header.len() + width * height];
+    assert_eq!(video.len(), header.len() + width * height * 3);
+    assert_eq!(&luma[..3], [255, 255, 0]);
+    assert_eq!(luma[width + 1], 255);
+}
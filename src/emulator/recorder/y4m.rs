@@ -0,0 +1,60 @@
+use std::io::{self, Write};
+
+const RGB_SIZE: usize = 3;
+const FRAME_TAG: &[u8] = b"FRAME\n";
+const CHROMA_OFFSET: f32 = 128.0;
+
+// 4:4:4 full range, so no chroma resolution or levels are lost
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(
+        mut out: W,
+        width: usize,
+        height: usize,
+        (numerator, denominator): (u32, u32),
+    ) -> io::Result<Self> {
+        writeln!(
+            out,
+            "YUV4MPEG2 W{width} H{height} F{numerator}:{denominator} Ip A1:1 C444 XCOLORRANGE=FULL"
+        )?;
+
+        Ok(Y4mWriter {
+            out,
+            planes: vec![0; width * height * RGB_SIZE],
+        })
+    }
+
+    pub fn write_frame(&mut self, rgb: &[u8]) -> io::Result<()> {
+        assert_eq!(rgb.len(), self.planes.len());
+
+        let pixels = rgb.len() / RGB_SIZE;
+        for (i, pixel) in rgb.chunks_exact(RGB_SIZE).enumerate() {
+            let (y, cb, cr) = to_ycbcr(pixel[0], pixel[1], pixel[2]);
+            self.planes[i] = y;
+            self.planes[pixels + i] = cb;
+            self.planes[2 * pixels + i] = cr;
+        }
+
+        self.out.write_all(FRAME_TAG)?;
+        self.out.write_all(&self.planes)
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+// BT.601 full range, the same matrix JPEG uses
+pub fn to_ycbcr(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let cb = CHROMA_OFFSET - 0.168_736 * r - 0.331_264 * g + 0.5 * b;
+    let cr = CHROMA_OFFSET + 0.5 * r - 0.418_688 * g - 0.081_312 * b;
+
+    let to_byte = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    (to_byte(y), to_byte(cb), to_byte(cr))
+}
@@ -1,5 +1,7 @@
 use std::mem;
 
+use super::ppu::nametables::{FOUR_SCREEN_VRAM_SIZE, Nametables};
+
 #[derive(Debug, Clone, Copy)]
 pub enum Mirroring {
     Vertical,
@@ -88,8 +90,13 @@ impl Rom {
         mem::take(&mut self.chr_rom)
     }
 
-    pub fn get_mirroring(&self) -> Mirroring {
-        self.screen_mirroring
+    // nametable memory as wired on the board, four-screen carts add their own VRAM
+    pub fn nametables(&self) -> Nametables {
+        let cartridge_vram = match self.screen_mirroring {
+            Mirroring::FourScreen => vec![0; FOUR_SCREEN_VRAM_SIZE],
+            _ => Vec::new(),
+        };
+        Nametables::new(self.screen_mirroring, cartridge_vram)
     }
 
     fn get_header_tag(header: &[u8]) -> &[u8] {
@@ -0,0 +1,156 @@
+use super::bus::Bus;
+use super::cpu::CPU6502;
+use super::joypad::Buttons;
+use super::rom::Rom;
+
+pub struct Input {
+    pub frame: usize,
+    pub button: Buttons,
+    pub pressed: bool,
+}
+
+pub struct Expectation {
+    pub frame: usize,
+    pub hash: u64,
+}
+
+pub struct Script {
+    pub rom: String,
+    pub frames: usize,
+    pub inputs: Vec<Input>,
+    pub expectations: Vec<Expectation>,
+}
+
+pub struct Capture {
+    pub frame: usize,
+    pub hash: u64,
+    pub screen: Vec<u8>,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Script, String> {
+        let mut script = Script {
+            rom: String::new(),
+            frames: 0,
+            inputs: Vec::new(),
+            expectations: Vec::new(),
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let err = |msg: &str| format!("line {}: {msg}: '{line}'", i + 1);
+
+            match fields.as_slice() {
+                [] => {}
+                ["rom", path] => script.rom = path.to_string(),
+                ["frames", frames] => script.frames = parse_frame(frames).map_err(|e| err(&e))?,
+                [action @ ("press" | "release"), frame, button] => script.inputs.push(Input {
+                    frame: parse_frame(frame).map_err(|e| err(&e))?,
+                    button: parse_button(button).map_err(|e| err(&e))?,
+                    pressed: *action == "press",
+                }),
+                ["expect", frame, hash] => script.expectations.push(Expectation {
+                    frame: parse_frame(frame).map_err(|e| err(&e))?,
+                    hash: u64::from_str_radix(hash, 16).map_err(|_| err("invalid screen hash"))?,
+                }),
+                _ => return Err(err("unknown command")),
+            }
+        }
+
+        if script.rom.is_empty() {
+            return Err("script does not name a rom".to_string());
+        }
+
+        let last_frame = script
+            .inputs
+            .iter()
+            .map(|input| input.frame)
+            .chain(script.expectations.iter().map(|exp| exp.frame))
+            .max()
+            .unwrap_or(0);
+        script.frames = script.frames.max(last_frame);
+
+        Ok(script)
+    }
+
+    pub fn run(&self, program: &[u8]) -> Result<Vec<Capture>, String> {
+        let capture_frames: Vec<usize> = self.expectations.iter().map(|exp| exp.frame).collect();
+        run(program, self.frames, &self.inputs, &capture_frames)
+    }
+}
+
+pub fn run(
+    program: &[u8],
+    frames: usize,
+    inputs: &[Input],
+    capture_frames: &[usize],
+) -> Result<Vec<Capture>, String> {
+    let rom = Rom::new(program)?;
+    let bus = Bus::new(rom, |_, _| {});
+    let mut cpu = CPU6502::new(bus);
+    cpu.reset();
+
+    let mut captures = Vec::with_capacity(capture_frames.len());
+    let mut frame = cpu.get_ppu().get_frame_count();
+
+    while frame < frames {
+        cpu.step();
+
+        while frame < cpu.get_ppu().get_frame_count() {
+            frame += 1;
+
+            for input in inputs.iter().filter(|input| input.frame == frame) {
+                let joypad = cpu.get_joypad_mut();
+                if input.pressed {
+                    joypad.set_button(input.button);
+                } else {
+                    joypad.unset_button(input.button);
+                }
+            }
+
+            if capture_frames.contains(&frame) {
+                let screen = cpu.get_ppu().screen.data.clone();
+                captures.push(Capture {
+                    frame,
+                    hash: hash_screen(&screen),
+                    screen,
+                });
+            }
+        }
+    }
+
+    Ok(captures)
+}
+
+pub fn hash_screen(data: &[u8]) -> u64 {
+    data.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+fn parse_frame(frame: &str) -> Result<usize, String> {
+    frame
+        .parse()
+        .map_err(|_| "invalid frame number".to_string())
+}
+
+fn parse_button(button: &str) -> Result<Buttons, String> {
+    match button.to_ascii_lowercase().as_str() {
+        "right" => Ok(Buttons::Right),
+        "left" => Ok(Buttons::Left),
+        "down" => Ok(Buttons::Down),
+        "up" => Ok(Buttons::Up),
+        "start" => Ok(Buttons::Start),
+        "select" => Ok(Buttons::Select),
+        "b" => Ok(Buttons::B),
+        "a" => Ok(Buttons::A),
+        _ => Err("unknown button".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::emulator::ppu::render::{frame::Frame, png};
+
+const SCRIPTS_DIR: &str = "roms/tests/screenshots";
+const MISMATCH_DIR: &str = "target/screenshot_mismatches";
+const SCRIPT_EXTENSION: &str = "script";
+
+fn collect_scripts(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut scripts: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+fn write_mismatch(name: &str, capture: &Capture) -> PathBuf {
+    let path = Path::new(MISMATCH_DIR).join(format!("{name}_frame{}.png", capture.frame));
+    let png = png::encode_rgb(Frame::WIDTH, Frame::HIGHT, &capture.screen);
+
+    std::fs::create_dir_all(MISMATCH_DIR).unwrap();
+    std::fs::write(&path, png).unwrap();
+    path
+}
+
+// every expectation needs exactly one capture of its frame, and every capture an expectation
+fn compare(name: &str, expectations: &[Expectation], captures: &[Capture]) -> Vec<String> {
+    let mut failures = Vec::new();
+    let mut by_frame: HashMap<usize, &Capture> = HashMap::new();
+
+    for capture in captures {
+        if by_frame.insert(capture.frame, capture).is_some() {
+            failures.push(format!("{name} frame {}: captured twice", capture.frame));
+        }
+    }
+
+    for expected in expectations {
+        match by_frame.remove(&expected.frame) {
+            None => failures.push(format!("{name} frame {}: not captured", expected.frame)),
+            Some(capture) if capture.hash != expected.hash => {
+                let png = write_mismatch(name, capture);
+                failures.push(format!(
+                    "{name} frame {}: expected {:016x}, got {:016x} (see {})",
+                    capture.frame,
+                    expected.hash,
+                    capture.hash,
+                    png.display()
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut unmatched: Vec<usize> = by_frame.into_keys().collect();
+    unmatched.sort();
+    for frame in unmatched {
+        failures.push(format!(
+            "{name} frame {frame}: captured without an expectation"
+        ));
+    }
+    failures
+}
+
+fn capture(frame: usize, hash: u64) -> Capture {
+    Capture {
+        frame,
+        hash,
+        screen: vec![0; Frame::WIDTH * Frame::HIGHT * 3],
+    }
+}
+
+#[test]
+fn parses_script() {
+    let script = Script::parse(
+        "rom game.nes # comment\n\
+         frames 10\n\
+         press 3 Start\n\
+         release 5 start\n\
+         expect 12 00ff\n",
+    )
+    .unwrap();
+
+    assert_eq!(script.rom, "game.nes");
+    assert_eq!(script.frames, 12);
+    assert_eq!(script.inputs.len(), 2);
+    assert!(script.inputs[0].pressed);
+    assert!(!script.inputs[1].pressed);
+    assert_eq!(script.expectations[0].hash, 0xFF);
+
+    assert!(Script::parse("frames 10").is_err());
+    assert!(Script::parse("rom a.nes\npress 1 Turbo").is_err());
+}
+
+#[test]
+fn hash_depends_on_every_byte() {
+    let mut screen = vec![0u8; Frame::WIDTH * Frame::HIGHT * 3];
+    let blank = hash_screen(&screen);
+
+    let last = screen.len() - 1;
+    screen[last] = 1;
+    assert_ne!(hash_screen(&screen), blank);
+}
+
+#[test]
+fn captures_are_matched_by_frame() {
+    let expectations = [
+        Expectation { frame: 5, hash: 1 },
+        Expectation { frame: 9, hash: 2 },
+    ];
+
+    let out_of_order = [capture(9, 2), capture(5, 1)];
+    assert!(compare("order", &expectations, &out_of_order).is_empty());
+
+    let missing = [capture(9, 2)];
+    assert_eq!(
+        compare("missing", &expectations, &missing),
+        ["missing frame 5: not captured"]
+    );
+
+    let extra = [capture(5, 1), capture(9, 2), capture(9, 2), capture(12, 3)];
+    assert_eq!(
+        compare("extra", &expectations, &extra),
+        [
+            "extra frame 9: captured twice",
+            "extra frame 12: captured without an expectation"
+        ]
+    );
+}
+
+#[test]
+fn screenshot_scripts() {
+    let scripts = collect_scripts(Path::new(SCRIPTS_DIR));
+    assert!(!scripts.is_empty(), "no scripts found in {SCRIPTS_DIR}");
+    let mut failures = Vec::new();
+
+    for path in &scripts {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let script = Script::parse(&std::fs::read_to_string(path).unwrap())
+            .unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+        let program = std::fs::read(&script.rom).unwrap();
+        let captures = script.run(&program).unwrap();
+        failures.extend(compare(&name, &script.expectations, &captures));
+    }
+
+    assert!(
+        failures.is_empty(),
+        "screenshot mismatches:\n{}",
+        failures.join("\n")
+    );
+}
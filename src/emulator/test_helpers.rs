@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::Path;
+
+use super::ppu::Ppu;
+use super::ppu::nametables::Nametables;
+use super::rom::Mirroring;
+
+const TEMP_DIR: &str = "nes_tests";
+const CHR_SIZE: usize = 0x2000;
+const TILE_BYTES: usize = 16;
+const TILE_PLANE_BYTES: usize = 8;
+
+pub const SOLID_TILE: u8 = 1;
+
+// a directory under the system temp dir, shared by every test that passes the same name
+pub fn temp_dir(name: &str) -> String {
+    let dir = std::env::temp_dir().join(TEMP_DIR).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir.to_string_lossy().into_owned()
+}
+
+// name inside temp_dir(dir), with whatever an earlier run left there removed
+pub fn temp_file(dir: &str, name: &str) -> String {
+    let path = Path::new(&temp_dir(dir)).join(name);
+    let _ = fs::remove_file(&path);
+    path.to_string_lossy().into_owned()
+}
+
+// CHR is blank except SOLID_TILE, which is colour 1 in every pixel
+pub fn solid_tile_ppu(mirroring: Mirroring) -> Ppu {
+    let mut chr = vec![0; CHR_SIZE];
+    let start = SOLID_TILE as usize * TILE_BYTES;
+    chr[start..start + TILE_PLANE_BYTES].fill(0xFF);
+    Ppu::new(chr, Nametables::new(mirroring, Vec::new()))
+}
+
+pub fn write_byte(ppu: &mut Ppu, addr: u16, value: u8) {
+    ppu.write_to_ppu_addr((addr >> 8) as u8);
+    ppu.write_to_ppu_addr(addr as u8);
+    ppu.write_to_data(value);
+}
+
+pub fn read_byte(ppu: &mut Ppu, addr: u16) -> u8 {
+    ppu.write_to_ppu_addr((addr >> 8) as u8);
+    ppu.write_to_ppu_addr(addr as u8);
+    ppu.read_data();
+    ppu.read_data()
+}
+
+// PPUADDR shares the scroll register, so point it back at the top left of nametable 0
+pub fn reset_scroll(ppu: &mut Ppu) {
+    ppu.write_to_ppu_addr(0);
+    ppu.write_to_ppu_addr(0);
+}
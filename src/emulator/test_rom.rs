@@ -0,0 +1,111 @@
+use super::bus::Bus;
+use super::cpu::CPU6502;
+use super::rom::Rom;
+
+pub struct TestRomResult {
+    pub status: TestRomStatus,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TestRomStatus {
+    Passed,
+    Failed(u8),
+    TimedOut,
+}
+
+const STATUS_ADDR: u16 = 0x6000;
+const SIGNATURE_ADDR: u16 = 0x6001;
+const TEXT_ADDR: u16 = 0x6004;
+const TEXT_MAX_LEN: u16 = 0x1FFC;
+
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_NEEDS_RESET: u8 = 0x81;
+const STATUS_PASSED: u8 = 0x00;
+
+const CPU_CYCLES_PER_SECOND: usize = 1789773;
+const RESET_DELAY_CYCLES: usize = CPU_CYCLES_PER_SECOND / 10;
+const DEFAULT_TIMEOUT_CYCLES: usize = CPU_CYCLES_PER_SECOND * 60;
+
+pub fn run(program: &[u8]) -> Result<TestRomResult, String> {
+    run_with_timeout(program, DEFAULT_TIMEOUT_CYCLES)
+}
+
+pub fn run_with_timeout(program: &[u8], timeout_cycles: usize) -> Result<TestRomResult, String> {
+    let rom = Rom::new(program)?;
+    let bus = Bus::new(rom, |_, _| {});
+    let mut cpu = CPU6502::new(bus);
+    cpu.reset();
+
+    let mut started = false;
+    let mut reset_at: Option<usize> = None;
+    let mut elapsed: usize = 0;
+
+    while elapsed < timeout_cycles {
+        let cycles_before = cpu.get_cycles_count();
+        cpu.step();
+        elapsed += cpu.get_cycles_count().wrapping_sub(cycles_before);
+
+        if let Some(deadline) = reset_at {
+            if elapsed >= deadline {
+                reset_at = None;
+                cpu.reset();
+            }
+            continue;
+        }
+
+        if !has_signature(&mut cpu) {
+            continue;
+        }
+
+        match cpu.mem_read(STATUS_ADDR) {
+            STATUS_RUNNING => started = true,
+            STATUS_NEEDS_RESET if started => {
+                started = false;
+                reset_at = Some(elapsed + RESET_DELAY_CYCLES);
+            }
+            status if started => {
+                let status = match status {
+                    STATUS_PASSED => TestRomStatus::Passed,
+                    code => TestRomStatus::Failed(code),
+                };
+
+                return Ok(TestRomResult {
+                    status,
+                    text: read_text(&mut cpu),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(TestRomResult {
+        status: TestRomStatus::TimedOut,
+        text: read_text(&mut cpu),
+    })
+}
+
+fn has_signature(cpu: &mut CPU6502) -> bool {
+    SIGNATURE
+        .iter()
+        .enumerate()
+        .all(|(i, byte)| cpu.mem_read(SIGNATURE_ADDR + i as u16) == *byte)
+}
+
+fn read_text(cpu: &mut CPU6502) -> String {
+    let mut text = Vec::new();
+
+    for offset in 0..TEXT_MAX_LEN {
+        let byte = cpu.mem_read(TEXT_ADDR + offset);
+        if byte == 0 {
+            break;
+        }
+        text.push(byte);
+    }
+
+    String::from_utf8_lossy(&text).trim().to_string()
+}
+
+#[cfg(test)]
+mod test;
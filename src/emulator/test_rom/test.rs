@@ -0,0 +1,133 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use super::*;
+
+const ACCURACY_ROMS_DIR: &str = "roms/tests/accuracy";
+
+fn protocol_rom(text: &[u8], result: u8) -> Vec<u8> {
+    const HEADER: [u8; 16] = [0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    const PRG_PAGE_SIZE: usize = 0x4000;
+    const CHR_PAGE_SIZE: usize = 0x2000;
+    const LDA_IMMEDIATE: u8 = 0xA9;
+    const STA_ABSOLUTE: u8 = 0x8D;
+    const JMP_ABSOLUTE: u8 = 0x4C;
+
+    let mut program = Vec::new();
+    let mut store = |value: u8, addr: u16| {
+        let [lo, hi] = addr.to_le_bytes();
+        program.extend_from_slice(&[LDA_IMMEDIATE, value, STA_ABSOLUTE, lo, hi]);
+    };
+
+    for (i, byte) in SIGNATURE.iter().enumerate() {
+        store(*byte, SIGNATURE_ADDR + i as u16);
+    }
+    store(STATUS_RUNNING, STATUS_ADDR);
+    for (i, byte) in text.iter().chain(&[0]).enumerate() {
+        store(*byte, TEXT_ADDR + i as u16);
+    }
+    store(result, STATUS_ADDR);
+
+    let [lo, hi] = (0x8000 + program.len() as u16).to_le_bytes();
+    program.extend_from_slice(&[JMP_ABSOLUTE, lo, hi]);
+
+    let mut prg_rom = vec![0u8; PRG_PAGE_SIZE];
+    prg_rom[..program.len()].copy_from_slice(&program);
+    prg_rom[PRG_PAGE_SIZE - 4] = 0x00;
+    prg_rom[PRG_PAGE_SIZE - 3] = 0x80;
+
+    let mut rom = HEADER.to_vec();
+    rom.extend_from_slice(&prg_rom);
+    rom.extend_from_slice(&[0u8; CHR_PAGE_SIZE]);
+    rom
+}
+
+fn collect_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_roms(&path, roms);
+        } else if path.extension().is_some_and(|ext| ext == "nes") {
+            roms.push(path);
+        }
+    }
+}
+
+#[test]
+fn reports_passing_rom() {
+    let result = run(&protocol_rom(b"Passed", STATUS_PASSED)).unwrap();
+
+    assert_eq!(result.status, TestRomStatus::Passed);
+    assert_eq!(result.text, "Passed");
+}
+
+#[test]
+fn reports_failing_rom() {
+    let result = run(&protocol_rom(b"Failed #3", 3)).unwrap();
+
+    assert_eq!(result.status, TestRomStatus::Failed(3));
+    assert_eq!(result.text, "Failed #3");
+}
+
+#[test]
+fn times_out_without_status() {
+    let rom = protocol_rom(b"", STATUS_RUNNING);
+    let result = run_with_timeout(&rom, CPU_CYCLES_PER_SECOND).unwrap();
+    assert_eq!(result.status, TestRomStatus::TimedOut);
+}
+
+#[test]
+fn rejects_invalid_rom() {
+    let mut rom = protocol_rom(b"", STATUS_PASSED);
+    rom[0] = 0;
+    assert!(run(&rom).is_err());
+}
+
+// the ROM set is not in the repository, see roms/tests/accuracy/README.md
+#[test]
+#[ignore = "needs the blargg ROM set in roms/tests/accuracy"]
+fn accuracy_roms_scoreboard() {
+    let mut roms = Vec::new();
+    collect_roms(Path::new(ACCURACY_ROMS_DIR), &mut roms);
+    roms.sort();
+
+    // an empty scoreboard would pass without testing anything
+    assert!(
+        !roms.is_empty(),
+        "no test ROMs found in {ACCURACY_ROMS_DIR}, see the README there"
+    );
+
+    let mut failures = Vec::new();
+    println!("accuracy scoreboard ({ACCURACY_ROMS_DIR}):");
+
+    for path in &roms {
+        let name = path
+            .strip_prefix(ACCURACY_ROMS_DIR)
+            .unwrap_or(path)
+            .display();
+        let program = std::fs::read(path).unwrap();
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| run(&program)));
+
+        let (passed, summary) = match outcome {
+            Ok(Ok(result)) => match result.status {
+                TestRomStatus::Passed => (true, "PASS".to_string()),
+                TestRomStatus::Failed(code) => (false, format!("FAIL #{code}: {}", result.text)),
+                TestRomStatus::TimedOut => (false, format!("TIMEOUT: {}", result.text)),
+            },
+            Ok(Err(err)) => (false, format!("ERROR: {err}")),
+            Err(_) => (false, "CRASH".to_string()),
+        };
+
+        println!("  {name:<40} {summary}");
+        if !passed {
+            failures.push(name.to_string());
+        }
+    }
+
+    println!("passed {}/{}", roms.len() - failures.len(), roms.len());
+    assert!(failures.is_empty(), "failing test ROMs: {failures:?}");
+}
@@ -1,37 +1,407 @@
 mod emulator;
 
+use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use emulator::apu::DEFAULT_SAMPLE_RATE;
+use emulator::apu::mixer::{Channel, Mixer};
 use emulator::bus::Bus;
+use emulator::cdl::CodeDataLog;
 use emulator::cpu::CPU6502;
 use emulator::joypad::{self, JoyPad};
 use emulator::ppu::Ppu;
+use emulator::ppu::layers::{Layers, NUM_OF_SPRITES};
 use emulator::ppu::render;
+use emulator::ppu::render::debug;
+use emulator::ppu::render::export::{self, Capture, DEFAULT_CHR_PALETTE, FrameDump};
 use emulator::ppu::render::frame::Frame;
+use emulator::ppu::render::framing::{AspectRatio, Overscan};
+use emulator::ppu::render::ntsc::{NtscFilter, NtscFilterSettings};
+use emulator::ppu::render::palette::{NtscPaletteSettings, Palette};
+use emulator::ppu::render::post::{Effect, Image, PostProcess, Scaler};
+use emulator::recorder::Recorder;
 use emulator::rom::Rom;
 
-// use emulator::cpu::trace;
+use emulator::cpu::trace::{TraceFilter, Tracer};
 
 use rand::Rng;
 use sdl3::EventPump;
+use sdl3::VideoSubsystem;
 use sdl3::audio::{AudioCallback, AudioFormat, AudioSpec, AudioStream};
-use sdl3::event::Event;
+use sdl3::event::{Event, WindowEvent};
 use sdl3::keyboard::Keycode;
 use sdl3::pixels::Color;
 use sdl3::pixels::PixelFormat;
+use sdl3::render::{Canvas, Texture, TextureCreator};
 use sdl3::sys::pixels::SDL_PixelFormat;
+use sdl3::video::{Window, WindowContext};
 
 #[macro_use]
 extern crate lazy_static;
 
+const DEFAULT_ROM_PATH: &str = "roms/games/super_mario.nes";
+const CDL_SAVE_INTERVAL_FRAMES: usize = 60;
+const NTSC_PALETTE: &str = "ntsc";
+const DEFAULT_SCALE: usize = 2;
+const WINDOW_SCALE: usize = 2;
+const DEBUG_WINDOW_SCALE: usize = 2;
+const EVENT_HOVER_RADIUS: usize = 2;
+const EXPORT_CHR_COMMAND: &str = "export-chr";
+const NES_COLOR_MASK: u8 = 0x3F;
+const SCREENSHOT_DIR: &str = ".";
+
+struct Options {
+    rom_path: String,
+    trace_path: Option<String>,
+    trace_filter: TraceFilter,
+    cdl_path: Option<String>,
+    sprite_limit: bool,
+    palette: Option<String>,
+    ntsc_palette: NtscPaletteSettings,
+    ntsc_filter: Option<NtscFilterSettings>,
+    post_process: PostProcess,
+    overscan: Overscan,
+    aspect: AspectRatio,
+    debug_viewers: bool,
+    layers: Layers,
+    dump_nametables: Vec<usize>,
+    dump_frames: Option<String>,
+    dump_interval: usize,
+    record: Option<String>,
+    mixer: Mixer,
+    sample_rate: u32,
+}
+
+struct ExportOptions {
+    rom_path: String,
+    prefix: Option<String>,
+    chr_palette: [u8; 4],
+    palette: Option<String>,
+}
+
 fn main() {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some(EXPORT_CHR_COMMAND) {
+        let options = match parse_export_args(args.skip(1)) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{err}");
+                eprintln!(
+                    "usage: nes export-chr ROM [--out PREFIX] [--chr-palette C0,C1,C2,C3] \
+                     [--palette FILE|ntsc]"
+                );
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = export_chr(options) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!(
+                "usage: nes [ROM] [--trace FILE|-] [--trace-pc START-END] [--trace-bank N] \
+                 [--trace-scanlines FIRST-LAST] [--trace-after ADDR] \
+                 [--cdl FILE] [--no-sprite-limit] [--palette FILE|ntsc] [--ntsc-hue DEG] \
+                 [--ntsc-saturation X] [--ntsc-contrast X] [--ntsc-brightness X] [--ntsc-gamma X] \
+                 [--ntsc-filter composite|svideo|rgb] [--ntsc-sharpness X] [--ntsc-fringing X] \
+                 [--ntsc-artifacts X] [--no-dot-crawl] \
+                 [--scaler nearest|bilinear|scale2x|scale3x|corner2x|xbr] [--scale N] \
+                 [--scanlines X] [--crt-mask X] [--overscan TOP,BOTTOM,LEFT,RIGHT|safe] \
+                 [--aspect square|8:7|4:3] [--debug-viewers] [--hide-background] \
+                 [--hide-sprites] [--hide-sprite N[,N...]] [--dump-nametables FRAME[,FRAME...]] \
+                 [--dump-frames DIR] [--dump-every N] [--record BASENAME] \
+                 [--mute CHANNEL[,CHANNEL...]] [--volume CHANNEL=X[,CHANNEL=X...]] \
+                 [--sample-rate HZ]\n\
+                 \x20      nes export-chr ROM [--out PREFIX] [--chr-palette C0,C1,C2,C3] \
+                 [--palette FILE|ntsc]\n\
+                 screenshots, --dump-frames and --record apply --overscan, --scaler and the \
+                 effects, but not --ntsc-filter"
+            );
+            std::process::exit(1);
+        }
+    };
+
     // snake_game();
     // tiles();
     // nes_test();
-    game_test();
+    game_test(options);
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        rom_path: DEFAULT_ROM_PATH.to_string(),
+        trace_path: None,
+        trace_filter: TraceFilter::default(),
+        cdl_path: None,
+        sprite_limit: true,
+        palette: None,
+        ntsc_palette: NtscPaletteSettings::default(),
+        ntsc_filter: None,
+        post_process: PostProcess::default(),
+        overscan: Overscan::default(),
+        aspect: AspectRatio::default(),
+        debug_viewers: false,
+        layers: Layers::default(),
+        dump_nametables: Vec::new(),
+        dump_frames: None,
+        dump_interval: 1,
+        record: None,
+        mixer: Mixer::default(),
+        sample_rate: DEFAULT_SAMPLE_RATE,
+    };
+    let mut scale = DEFAULT_SCALE;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+
+        match arg.as_str() {
+            "--trace" => options.trace_path = Some(value()?),
+            "--trace-pc" => options.trace_filter.pc_range = Some(parse_hex_range(&value()?)?),
+            "--trace-bank" => {
+                options.trace_filter.bank =
+                    Some(value()?.parse().map_err(|_| "invalid bank number")?)
+            }
+            "--trace-scanlines" => {
+                let range = value()?;
+                let (first, last) = range.split_once('-').ok_or("expected FIRST-LAST")?;
+                let first = first.parse().map_err(|_| "invalid scanline")?;
+                let last = last.parse().map_err(|_| "invalid scanline")?;
+                options.trace_filter.scanline_range = Some(first..=last);
+            }
+            "--trace-after" => options.trace_filter.start_addr = Some(parse_hex(&value()?)?),
+            "--cdl" => options.cdl_path = Some(value()?),
+            "--no-sprite-limit" => options.sprite_limit = false,
+            "--palette" => options.palette = Some(value()?),
+            "--ntsc-hue" => options.ntsc_palette.hue = parse_float(&value()?)?,
+            "--ntsc-saturation" => options.ntsc_palette.saturation = parse_float(&value()?)?,
+            "--ntsc-contrast" => options.ntsc_palette.contrast = parse_float(&value()?)?,
+            "--ntsc-brightness" => options.ntsc_palette.brightness = parse_float(&value()?)?,
+            "--ntsc-gamma" => options.ntsc_palette.gamma = parse_float(&value()?)?,
+            "--ntsc-filter" => {
+                options.ntsc_filter = Some(match value()?.as_str() {
+                    "composite" => NtscFilterSettings::composite(),
+                    "svideo" => NtscFilterSettings::svideo(),
+                    "rgb" => NtscFilterSettings::rgb(),
+                    preset => return Err(format!("unknown ntsc filter {preset}")),
+                })
+            }
+            "--ntsc-sharpness" => {
+                ntsc_filter_settings(&mut options).sharpness = parse_float(&value()?)?
+            }
+            "--ntsc-fringing" => {
+                ntsc_filter_settings(&mut options).fringing = parse_float(&value()?)?
+            }
+            "--ntsc-artifacts" => {
+                ntsc_filter_settings(&mut options).artifacts = parse_float(&value()?)?
+            }
+            "--no-dot-crawl" => ntsc_filter_settings(&mut options).dot_crawl = false,
+            "--scaler" => {
+                options.post_process.scaler = match value()?.as_str() {
+                    "nearest" => Scaler::Nearest(scale),
+                    "bilinear" => Scaler::Bilinear(scale),
+                    "scale2x" => Scaler::Scale2x,
+                    "scale3x" => Scaler::Scale3x,
+                    "corner2x" => Scaler::CornerBlend2x,
+                    "xbr" => Scaler::Xbr2x,
+                    scaler => return Err(format!("unknown scaler {scaler}")),
+                }
+            }
+            "--scale" => {
+                scale = value()?.parse().map_err(|_| "invalid scale factor")?;
+                if let Scaler::Nearest(factor) | Scaler::Bilinear(factor) =
+                    &mut options.post_process.scaler
+                {
+                    *factor = scale;
+                }
+            }
+            "--scanlines" => options
+                .post_process
+                .effects
+                .push(Effect::Scanlines(parse_float(&value()?)?)),
+            "--crt-mask" => options
+                .post_process
+                .effects
+                .push(Effect::CrtMask(parse_float(&value()?)?)),
+            "--debug-viewers" => options.debug_viewers = true,
+            "--hide-background" => options.layers.hide_background = true,
+            "--hide-sprites" => options.layers.hide_sprites = true,
+            "--hide-sprite" => {
+                for index in value()?.split(',') {
+                    let index = index
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|index| *index < NUM_OF_SPRITES)
+                        .ok_or(format!("invalid sprite index {index}"))?;
+                    options.layers.hide_sprite(index);
+                }
+            }
+            "--dump-nametables" => {
+                for frame in value()?.split(',') {
+                    let frame = frame
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid frame number {frame}"))?;
+                    options.dump_nametables.push(frame);
+                }
+            }
+            "--dump-frames" => options.dump_frames = Some(value()?),
+            "--dump-every" => {
+                options.dump_interval = value()?.parse().map_err(|_| "invalid frame interval")?;
+                if options.dump_interval == 0 {
+                    return Err("frame interval must be above 0".to_string());
+                }
+            }
+            "--record" => options.record = Some(value()?),
+            "--mute" => {
+                for channel in value()?.split(',') {
+                    options.mixer.set_muted(parse_channel(channel)?, true);
+                }
+            }
+            "--sample-rate" => {
+                options.sample_rate = value()?.parse().map_err(|_| "invalid sample rate")?;
+                if options.sample_rate == 0 {
+                    return Err("sample rate must be above 0".to_string());
+                }
+            }
+            "--volume" => {
+                for setting in value()?.split(',') {
+                    let (channel, volume) = setting
+                        .split_once('=')
+                        .ok_or(format!("volume {setting} needs CHANNEL=X"))?;
+                    options
+                        .mixer
+                        .set_volume(parse_channel(channel)?, parse_float(volume)?);
+                }
+            }
+            "--overscan" => options.overscan = parse_overscan(&value()?)?,
+            "--aspect" => {
+                options.aspect = match value()?.as_str() {
+                    "square" => AspectRatio::Square,
+                    "8:7" => AspectRatio::Pixel8To7,
+                    "4:3" => AspectRatio::Display4To3,
+                    aspect => return Err(format!("unknown aspect ratio {aspect}")),
+                }
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => options.rom_path = arg,
+        }
+    }
+
+    let (width, height) = options.overscan.visible_size();
+    if width == 0 || height == 0 {
+        return Err("overscan crops the whole frame".to_string());
+    }
+
+    if let Some(filter) = options.ntsc_filter.as_mut() {
+        filter.palette = options.ntsc_palette;
+    }
+
+    Ok(options)
+}
+
+fn parse_export_args(mut args: impl Iterator<Item = String>) -> Result<ExportOptions, String> {
+    let mut rom_path = None;
+    let mut options = ExportOptions {
+        rom_path: String::new(),
+        prefix: None,
+        chr_palette: DEFAULT_CHR_PALETTE,
+        palette: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+
+        match arg.as_str() {
+            "--out" => options.prefix = Some(value()?),
+            "--chr-palette" => options.chr_palette = parse_chr_palette(&value()?)?,
+            "--palette" => options.palette = Some(value()?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => rom_path = Some(arg),
+        }
+    }
+
+    options.rom_path = rom_path.ok_or("missing ROM path")?;
+    Ok(options)
+}
+
+// four NES colour indices in hex, e.g. 0F,00,10,30
+fn parse_chr_palette(val: &str) -> Result<[u8; 4], String> {
+    let colors = val
+        .split(',')
+        .map(|color| u8::from_str_radix(color.trim().trim_start_matches('$'), 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("invalid chr palette {val}"))?;
+
+    match colors[..] {
+        [c0, c1, c2, c3] if colors.iter().all(|&color| color <= NES_COLOR_MASK) => {
+            Ok([c0, c1, c2, c3])
+        }
+        _ => Err(format!(
+            "chr palette {val} needs four colours from 00 to 3F"
+        )),
+    }
+}
+
+// tuning flags on their own turn on the composite filter
+fn ntsc_filter_settings(options: &mut Options) -> &mut NtscFilterSettings {
+    options
+        .ntsc_filter
+        .get_or_insert_with(NtscFilterSettings::composite)
+}
+
+fn parse_hex(val: &str) -> Result<u16, String> {
+    let digits = val.trim_start_matches('$').trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address {val}"))
+}
+
+fn parse_float(val: &str) -> Result<f32, String> {
+    val.parse().map_err(|_| format!("invalid number {val}"))
+}
+
+// "safe" is the NTSC safe area, otherwise one value per edge
+fn parse_overscan(val: &str) -> Result<Overscan, String> {
+    if val == "safe" {
+        return Ok(Overscan::ntsc_safe_area());
+    }
+
+    let edges = val
+        .split(',')
+        .map(|edge| edge.trim().parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("invalid overscan {val}"))?;
+
+    match edges[..] {
+        [top, bottom, left, right] => Ok(Overscan {
+            top,
+            bottom,
+            left,
+            right,
+        }),
+        _ => Err(format!("overscan {val} needs TOP,BOTTOM,LEFT,RIGHT")),
+    }
+}
+
+fn parse_channel(val: &str) -> Result<Channel, String> {
+    Channel::from_name(val.trim()).ok_or(format!(
+        "unknown channel {val}, expected pulse1, pulse2, triangle, noise or dmc"
+    ))
+}
+
+fn parse_hex_range(val: &str) -> Result<std::ops::RangeInclusive<u16>, String> {
+    let (start, end) = val.split_once('-').ok_or("expected START-END")?;
+    Ok(parse_hex(start)?..=parse_hex(end)?)
 }
 
 fn nes_test() {
@@ -132,29 +502,226 @@ impl AudioCallback<f32> for NesAudioCallback {
     }
 }
 
-fn game_test() {
+struct DebugWindow {
+    canvas: Canvas<Window>,
+    texture: Texture<'static>,
+    open: bool,
+}
+
+impl DebugWindow {
+    fn new(video: &VideoSubsystem, title: &str, width: usize, height: usize) -> Self {
+        let window = video
+            .window(
+                title,
+                (width * DEBUG_WINDOW_SCALE) as u32,
+                (height * DEBUG_WINDOW_SCALE) as u32,
+            )
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas();
+        // the viewers live until the emulator exits, leaking the creator lets each keep its texture
+        let creator: &'static TextureCreator<WindowContext> =
+            Box::leak(Box::new(canvas.texture_creator()));
+        let pixel_format = unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) };
+        let texture = creator
+            .create_texture_streaming(pixel_format, width as u32, height as u32)
+            .unwrap();
+
+        DebugWindow {
+            canvas,
+            texture,
+            open: true,
+        }
+    }
+
+    fn get_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    // a closed viewer is only hidden, the game and the other viewers keep running
+    fn close(&mut self) {
+        self.canvas.window_mut().hide();
+        self.open = false;
+    }
+
+    fn show(&mut self, image: &Image) {
+        if !self.open {
+            return;
+        }
+        self.texture
+            .update(None, &image.data, image.width * 3)
+            .unwrap();
+
+        self.canvas.copy(&self.texture, None, None).unwrap();
+        self.canvas.present();
+    }
+}
+
+// P cycles the pattern table palette, O prints the OAM list,
+// hovering the event viewer lists the writes under the cursor in its title
+struct DebugViewers {
+    nametables: DebugWindow,
+    pattern_tables: DebugWindow,
+    oam: DebugWindow,
+    palettes: DebugWindow,
+    events: DebugWindow,
+    pattern_palette: usize,
+    print_oam: bool,
+    event_hover: Option<(u16, usize)>,
+}
+
+impl DebugViewers {
+    fn new(video: &VideoSubsystem) -> Self {
+        DebugViewers {
+            nametables: DebugWindow::new(
+                video,
+                "Nametables",
+                debug::NAMETABLES_WIDTH,
+                debug::NAMETABLES_HEIGHT,
+            ),
+            pattern_tables: DebugWindow::new(
+                video,
+                "Pattern Tables",
+                debug::PATTERN_TABLES_WIDTH,
+                debug::PATTERN_TABLES_HEIGHT,
+            ),
+            oam: DebugWindow::new(video, "OAM", debug::OAM_WIDTH, debug::OAM_HEIGHT),
+            palettes: DebugWindow::new(
+                video,
+                "Palettes",
+                debug::PALETTES_WIDTH,
+                debug::PALETTES_HEIGHT,
+            ),
+            events: DebugWindow::new(video, "Events", debug::EVENTS_WIDTH, debug::EVENTS_HEIGHT),
+            pattern_palette: 0,
+            print_oam: false,
+            event_hover: None,
+        }
+    }
+
+    fn handle_key(&mut self, keycode: Keycode) {
+        match keycode {
+            Keycode::P => {
+                self.pattern_palette = (self.pattern_palette + 1) % debug::NUM_OF_PALETTES
+            }
+            Keycode::O => self.print_oam = true,
+            _ => {}
+        }
+    }
+
+    fn handle_mouse(&mut self, window_id: u32, x: f32, y: f32) {
+        if window_id == self.events.get_id() {
+            let scanline = y as usize / DEBUG_WINDOW_SCALE;
+            let dot = x as usize / DEBUG_WINDOW_SCALE;
+            self.event_hover = Some((scanline as u16, dot));
+        }
+    }
+
+    fn close(&mut self, window_id: u32) {
+        for window in [
+            &mut self.nametables,
+            &mut self.pattern_tables,
+            &mut self.oam,
+            &mut self.palettes,
+            &mut self.events,
+        ] {
+            if window.get_id() == window_id {
+                window.close();
+            }
+        }
+    }
+
+    // the sprite under a click in the OAM window
+    fn clicked_sprite(&self, window_id: u32, x: f32, y: f32) -> Option<u8> {
+        if window_id != self.oam.get_id() {
+            return None;
+        }
+        debug::oam_sprite_at(
+            x as usize / DEBUG_WINDOW_SCALE,
+            y as usize / DEBUG_WINDOW_SCALE,
+        )
+    }
+
+    fn update(&mut self, ppu: &Ppu) {
+        self.nametables.show(&debug::nametables(ppu));
+        self.events.show(&debug::events(ppu));
+        if let (Some((scanline, dot)), Some(log)) = (self.event_hover, ppu.get_event_log()) {
+            let hovered: Vec<String> = log
+                .events_near(scanline, dot, EVENT_HOVER_RADIUS)
+                .iter()
+                .map(debug::describe_event)
+                .collect();
+            let title = format!("Events {scanline}:{dot} {}", hovered.join(", "));
+            self.events.canvas.window_mut().set_title(&title).unwrap();
+        }
+        self.pattern_tables
+            .show(&debug::pattern_tables(ppu, self.pattern_palette));
+        self.oam.show(&debug::oam(ppu));
+        self.palettes.show(&debug::palettes(ppu));
+
+        if std::mem::take(&mut self.print_oam) {
+            println!(" #   X   Y TILE ATTR");
+            for sprite in 0..ppu.get_oam().len() / 4 {
+                let [y, tile, attr, x] = debug::sprite_bytes(ppu.get_oam(), sprite);
+                println!("{sprite:2} {x:3} {y:3}   {tile:02X}   {attr:02X}");
+            }
+        }
+    }
+}
+
+fn game_test(options: Options) {
     let sdl_context = sdl3::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
+    let overscan = options.overscan;
+    let (visible_width, visible_height) = overscan.visible_size();
+    let (display_width, display_height) =
+        options.aspect.display_size(visible_width, visible_height);
     let window = video_subsystem
-        .window("Game", 256 * 2, 240 * 2)
+        .window(
+            "Game",
+            (display_width * WINDOW_SCALE) as u32,
+            (display_height * WINDOW_SCALE) as u32,
+        )
         .position_centered()
         .build()
         .unwrap();
 
     let mut canvas = window.into_canvas();
+    let main_window_id = canvas.window().id();
     let mut event_pump = sdl_context.event_pump().unwrap();
-    canvas.set_scale(2.0, 2.0).unwrap();
+    let mut debug_viewers = options
+        .debug_viewers
+        .then(|| DebugViewers::new(&video_subsystem));
 
     let pixel_format = unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) };
     let creator = canvas.texture_creator();
+    let mut ntsc_filter = options.ntsc_filter.map(NtscFilter::new);
+    let texture_width = if ntsc_filter.is_some() {
+        NtscFilter::WIDTH
+    } else {
+        Frame::WIDTH
+    };
+    let cropped = overscan.crop(&Image::new(texture_width, Frame::HIGHT));
+    let post_process = options.post_process;
+    let factor = post_process.scaler.factor();
+    // screenshots, frame dumps and recordings get the same crop, scaler and effects as the window,
+    // but not the NTSC filter
+    let capture = Capture {
+        overscan,
+        post_process: post_process.clone(),
+    };
     let mut texture = creator
-        .create_texture_target(pixel_format, 256, 240)
+        .create_texture_target(
+            pixel_format,
+            (cropped.width * factor) as u32,
+            (cropped.height * factor) as u32,
+        )
         .unwrap();
 
     // Audio
     let audio_subsystem = sdl_context.audio().unwrap();
     let desired_spec = AudioSpec {
-        freq: Some(44100),
+        freq: Some(options.sample_rate as i32),
         channels: Some(1),
         format: Some(AudioFormat::F32LE),
     };
@@ -173,9 +740,22 @@ fn game_test() {
 
     audio_device.resume().unwrap();
 
-    let program = std::fs::read("roms/games/super_mario.nes").unwrap();
+    let program = std::fs::read(&options.rom_path).unwrap();
     let rom = Rom::new(&program).unwrap();
 
+    let mut tracer = options.trace_path.map(|path| {
+        Tracer::to_file(&path, options.trace_filter).expect("Failed to open trace output")
+    });
+
+    // continue an existing log so coverage accumulates across sessions
+    let cdl = options.cdl_path.as_ref().map(|path| {
+        CodeDataLog::load_or_new(path, rom.prg_size(), rom.chr_size()).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            std::process::exit(1);
+        })
+    });
+    let mut cdl_frame = 0;
+
     // let mut frame = Frame::new();
 
     let mut key_map = HashMap::new();
@@ -191,22 +771,146 @@ fn game_test() {
     let mut time = Instant::now();
     const FRAME_RATE: f32 = 1.0 / 60.0;
 
+    // F1 and F2 flip the background and sprite layers, the cpu loop hands them to the PPU
+    let layers = Rc::new(Cell::new(options.layers));
+    let frontend_layers = layers.clone();
+
+    // F9 starts and stops recording, quitting goes through the cpu loop so the files get finished
+    let recording = Rc::new(Cell::new(options.record.is_some()));
+    let frontend_recording = recording.clone();
+    let quit = Rc::new(Cell::new(false));
+    let frontend_quit = quit.clone();
+
+    // 1-5 mute and unmute pulse 1, pulse 2, triangle, noise and DMC
+    let mixer = Rc::new(Cell::new(options.mixer));
+    let frontend_mixer = mixer.clone();
+    let mut mute_keys = HashMap::new();
+    mute_keys.insert(Keycode::_1, Channel::Pulse1);
+    mute_keys.insert(Keycode::_2, Channel::Pulse2);
+    mute_keys.insert(Keycode::_3, Channel::Triangle);
+    mute_keys.insert(Keycode::_4, Channel::Noise);
+    mute_keys.insert(Keycode::_5, Channel::Dmc);
+
+    let frame_dump = options
+        .dump_frames
+        .as_ref()
+        .map(|dir| FrameDump::new(dir, options.dump_interval, capture.clone()))
+        .transpose();
+    let mut frame_dump = match frame_dump {
+        Ok(frame_dump) => frame_dump,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    let frontend_capture = capture.clone();
+
     let bus = Bus::new(rom, move |ppu: &Ppu, joypad: &mut JoyPad| {
         // render::render(ppu, &mut frame);
-        texture.update(None, &ppu.screen.data, 256 * 3).unwrap();
+        let image = if let Some(filter) = ntsc_filter.as_mut() {
+            filter.render(&ppu.screen_indices, ppu.get_frame_count());
+            Image::from_rgb(NtscFilter::WIDTH, Frame::HIGHT, &filter.data)
+        } else {
+            Image::from_frame(&ppu.screen)
+        };
+        let image = post_process.apply(&overscan.crop(&image));
+        texture.update(None, &image.data, image.width * 3).unwrap();
 
         canvas.copy(&texture, None, None).unwrap();
         canvas.present();
 
+        if let Some(viewers) = debug_viewers.as_mut() {
+            viewers.update(ppu);
+        }
+        if options.dump_nametables.contains(&ppu.get_frame_count()) {
+            dump_nametables(ppu);
+        }
+        // a failed write stops the dump, the game keeps running
+        if let Some(Err(err)) = frame_dump.as_ref().map(|dump| dump.capture(ppu)) {
+            eprintln!("{err}, frame dump stopped");
+            frame_dump = None;
+        }
+
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
-                } => std::process::exit(0),
+                } => frontend_quit.set(true),
+                // closing a viewer leaves the game running, closing the game window quits
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::CloseRequested,
+                    ..
+                } => {
+                    if window_id == main_window_id {
+                        frontend_quit.set(true);
+                    } else if let Some(viewers) = debug_viewers.as_mut() {
+                        viewers.close(window_id);
+                    }
+                }
 
+                Event::MouseMotion {
+                    window_id, x, y, ..
+                } => {
+                    if let Some(viewers) = debug_viewers.as_mut() {
+                        viewers.handle_mouse(window_id, x, y);
+                    }
+                }
+                // clicking a sprite in the OAM window hides or shows it
+                Event::MouseButtonDown {
+                    window_id, x, y, ..
+                } => {
+                    let clicked = debug_viewers
+                        .as_ref()
+                        .and_then(|viewers| viewers.clicked_sprite(window_id, x, y));
+                    if let Some(index) = clicked {
+                        let mut toggled = frontend_layers.get();
+                        toggled.toggle_sprite(index);
+                        frontend_layers.set(toggled);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F1),
+                    ..
+                } => {
+                    let mut toggled = frontend_layers.get();
+                    toggled.hide_background = !toggled.hide_background;
+                    frontend_layers.set(toggled);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    ..
+                } => {
+                    let mut toggled = frontend_layers.get();
+                    toggled.hide_sprites = !toggled.hide_sprites;
+                    frontend_layers.set(toggled);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    ..
+                } => dump_nametables(ppu),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    ..
+                } => frontend_recording.set(!frontend_recording.get()),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    ..
+                } => match export::save_screenshot(ppu, SCREENSHOT_DIR, &frontend_capture) {
+                    Ok(path) => println!("wrote {path}"),
+                    Err(err) => eprintln!("{err}"),
+                },
                 Event::KeyDown { keycode, .. } => {
+                    if let (Some(viewers), Some(keycode)) = (debug_viewers.as_mut(), keycode) {
+                        viewers.handle_key(keycode);
+                    }
+                    if let Some(&channel) = mute_keys.get(&keycode.unwrap_or(Keycode::Ampersand)) {
+                        let mut toggled = frontend_mixer.get();
+                        toggled.set_muted(channel, !toggled.is_muted(channel));
+                        frontend_mixer.set(toggled);
+                    }
                     if let Some(button) = key_map.get(&keycode.unwrap_or(Keycode::Ampersand)) {
                         joypad.set_button(*button);
                     }
@@ -225,95 +929,153 @@ fn game_test() {
     });
 
     let mut cpu = CPU6502::new(bus);
+    if let Some(cdl) = cdl {
+        cpu.enable_code_data_log(cdl);
+    }
+    cpu.get_ppu_mut().set_sprite_limit(options.sprite_limit);
+    if options.debug_viewers {
+        cpu.get_ppu_mut().enable_event_log();
+    }
+    match options.palette.as_deref() {
+        Some(NTSC_PALETTE) => cpu
+            .get_ppu_mut()
+            .set_palette(Palette::generate_ntsc(&options.ntsc_palette)),
+        Some(path) => cpu
+            .get_ppu_mut()
+            .set_palette(Palette::load(path).expect("Failed to load palette")),
+        None => {}
+    }
+    cpu.get_apu_mut().set_sample_rate(options.sample_rate);
+    if let Some(basename) = &options.record {
+        start_recording(&mut cpu, basename, &capture);
+    }
     cpu.reset();
     cpu.run_with_callback(move |cpu| {
+        if cpu.get_ppu().get_layers() != layers.get() {
+            cpu.get_ppu_mut().set_layers(layers.get());
+        }
+        if cpu.get_apu().get_mixer() != mixer.get() {
+            cpu.get_apu_mut().set_mixer(mixer.get());
+        }
+
+        if recording.get() != cpu.is_recording() {
+            if recording.get() {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                let basename = format!("recording_{}", now.as_secs());
+                if !start_recording(cpu, &basename, &capture) {
+                    recording.set(false);
+                }
+            } else {
+                stop_recording(cpu);
+            }
+        }
+        if quit.get() {
+            stop_recording(cpu);
+            if let Some(tracer) = tracer.as_mut() {
+                tracer.flush().expect("Failed to write trace");
+            }
+            std::process::exit(0);
+        }
+
+        if let Some(tracer) = tracer.as_mut() {
+            tracer.trace(cpu).expect("Failed to write trace");
+        }
+
+        // the process exits from the frame callback, so save the log periodically
+        if let Some(path) = &options.cdl_path {
+            let frame = cpu.get_ppu().get_frame_count();
+            if frame >= cdl_frame + CDL_SAVE_INTERVAL_FRAMES {
+                cdl_frame = frame;
+                let cdl = cpu.get_code_data_log().unwrap();
+                cdl.save(path).expect("Failed to write code/data log");
+            }
+        }
+
         if cpu.get_nof_samples() >= 1024 {
             let samples = cpu.get_apu_samples();
             let mut buffer = audio_buffer.lock().unwrap();
 
-            if buffer.len() < 44100 {
+            // hold at most a second of audio
+            if buffer.len() < options.sample_rate as usize {
                 buffer.extend(samples);
             }
         }
     });
 }
 
-use emulator::ppu::render::pallete_table as palette;
+fn export_chr(options: ExportOptions) -> Result<(), String> {
+    let program =
+        std::fs::read(&options.rom_path).map_err(|err| format!("{}: {err}", options.rom_path))?;
+    let mut rom = Rom::new(&program)?;
 
-fn show_tile(chr_rom: &Vec<u8>, bank: usize, tile_n: usize) -> Frame {
-    assert!(bank <= 1);
+    let palette = match options.palette.as_deref() {
+        Some(NTSC_PALETTE) => Palette::generate_ntsc(&NtscPaletteSettings::default()),
+        Some(path) => Palette::load(path)?,
+        None => Palette::default(),
+    };
+    let colors = export::chr_colors(&palette, options.chr_palette);
+    // next to the ROM unless told otherwise
+    let prefix = options.prefix.unwrap_or_else(|| {
+        Path::new(&options.rom_path)
+            .with_extension("")
+            .to_string_lossy()
+            .into_owned()
+    });
 
-    let mut frame = Frame::new();
-    let bank = bank * 0x1000;
-
-    let tile = &chr_rom[(bank + tile_n * 16)..=(bank + tile_n * 16 + 15)];
-
-    for y in 0..=7 {
-        let mut upper = tile[y];
-        let mut lower = tile[y + 8];
-
-        for x in (0..=7).rev() {
-            let value = (1 & upper) << 1 | (1 & lower);
-            upper >>= 1;
-            lower >>= 1;
-            let rgb = match value {
-                0 => palette::SYSTEM_PALLETE[0x01],
-                1 => palette::SYSTEM_PALLETE[0x23],
-                2 => palette::SYSTEM_PALLETE[0x27],
-                3 => palette::SYSTEM_PALLETE[0x30],
-                _ => panic!("can't be"),
-            };
-            frame.set_pixel(x, y, rgb)
-        }
+    for path in export::export_chr(&rom.take_chr_rom(), &colors, &prefix)? {
+        println!("wrote {path}");
     }
+    Ok(())
+}
 
-    frame
+// BASENAME.y4m and BASENAME.wav
+fn start_recording(cpu: &mut CPU6502, basename: &str, capture: &Capture) -> bool {
+    let (video, audio) = (format!("{basename}.y4m"), format!("{basename}.wav"));
+    let sample_rate = cpu.get_apu().get_sample_rate();
+    match Recorder::to_files(&video, &audio, sample_rate, capture.clone()) {
+        Ok(recorder) => {
+            println!("recording to {video} and {audio}");
+            cpu.start_recording(recorder);
+            true
+        }
+        Err(err) => {
+            eprintln!("{basename}: {err}");
+            false
+        }
+    }
 }
 
-fn show_tile_bank(chr_rom: &Vec<u8>, bank: usize) -> Frame {
-    assert!(bank <= 1);
+fn stop_recording(cpu: &mut CPU6502) {
+    let Some(recorder) = cpu.get_recorder() else {
+        return;
+    };
+    let (frames, samples) = (recorder.get_frames(), recorder.get_samples());
 
-    let mut frame = Frame::new();
-    let mut tile_y = 0;
-    let mut tile_x = 0;
-    let bank = (bank * 0x1000) as usize;
-
-    for tile_n in 0..255 {
-        if tile_n != 0 && tile_n % 20 == 0 {
-            tile_y += 10;
-            tile_x = 0;
-        }
-        let tile = &chr_rom[(bank + tile_n * 16)..=(bank + tile_n * 16 + 15)];
-
-        for y in 0..=7 {
-            let mut upper = tile[y];
-            let mut lower = tile[y + 8];
-
-            for x in (0..=7).rev() {
-                let value = (1 & upper) << 1 | (1 & lower);
-                upper = upper >> 1;
-                lower = lower >> 1;
-                let rgb = match value {
-                    0 => palette::SYSTEM_PALLETE[0x01],
-                    1 => palette::SYSTEM_PALLETE[0x23],
-                    2 => palette::SYSTEM_PALLETE[0x27],
-                    3 => palette::SYSTEM_PALLETE[0x30],
-                    _ => panic!("can't be"),
-                };
-                frame.set_pixel(tile_x + x, tile_y + y, rgb)
-            }
-        }
+    match cpu.stop_recording() {
+        Ok(()) => println!("recorded {frames} frames and {samples} samples"),
+        Err(err) => eprintln!("recording failed: {err}"),
+    }
+}
 
-        tile_x += 10;
+fn dump_nametables(ppu: &Ppu) {
+    let path = format!("nametables_frame{}.png", ppu.get_frame_count());
+    match export::export_nametables(ppu, &path) {
+        Ok(()) => println!("wrote {path}"),
+        Err(err) => eprintln!("{err}"),
     }
-    frame
 }
 
 fn tiles() {
     let sdl_context = sdl3::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window = video_subsystem
-        .window("Tile View", 256 * 3, 240 * 3)
+        .window(
+            "Tile View",
+            (debug::CHR_BANK_SIZE * 3) as u32,
+            (debug::CHR_BANK_SIZE * 3) as u32,
+        )
         .position_centered()
         .build()
         .unwrap();
@@ -325,15 +1087,22 @@ fn tiles() {
     let pixel_format = unsafe { PixelFormat::from_ll(SDL_PixelFormat::RGB24) };
     let creator = canvas.texture_creator();
     let mut texture = creator
-        .create_texture_target(pixel_format, 256, 240)
+        .create_texture_target(
+            pixel_format,
+            debug::CHR_BANK_SIZE as u32,
+            debug::CHR_BANK_SIZE as u32,
+        )
         .unwrap();
 
     let program = std::fs::read("roms/games/Pac-Man.nes").unwrap();
     let mut rom = Rom::new(&program).unwrap();
 
-    let right_bank = show_tile_bank(&rom.take_chr_rom(), 0);
+    let colors = export::chr_colors(&Palette::default(), DEFAULT_CHR_PALETTE);
+    let right_bank = debug::chr_bank(&rom.take_chr_rom(), 0, &colors);
 
-    texture.update(None, &right_bank.data, 256 * 3).unwrap();
+    texture
+        .update(None, &right_bank.data, right_bank.width * 3)
+        .unwrap();
     canvas.copy(&texture, None, None).unwrap();
     canvas.present();
 